@@ -4,33 +4,604 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
 use std::env::var;
-use std::fs::{File, OpenOptions};
+use std::fs::{read, read_dir, read_to_string, write, File};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use png2wasm4src::build_sprite_modules_tree;
+use png::{BitDepth, ColorType, Decoder};
+
+/// The directory containing the sprites
+const SPRITES_DIRECTORY: &str = "assets/sprites";
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let module = build_sprite_modules_tree("assets/sprites")?;
+    let sprites = load_sprites(SPRITES_DIRECTORY)?;
+    write_sprites(&sprites)?;
+
+    write_embedded_snapshot()?;
+
+    let font = sprites
+        .iter()
+        .find(|sprite| sprite.name == FONT_NAME)
+        .ok_or_else(|| format!("Missing font sprite {}.png", FONT_NAME))?;
+    write_glyph_widths(font)?;
+
+    write_animations(SPRITES_DIRECTORY)?;
+
+    Ok(())
+}
+
+/// The largest number of pixels of a sprite packed in an atlas
+const MAX_ATLAS_SPRITE_AREA: u32 = 32 * 32;
+
+/// The smallest width of an atlas
+const MIN_ATLAS_WIDTH: u32 = 64;
+
+/// A sprite read from a PNG file
+struct SpriteImage {
+    name: String,
+    path: PathBuf,
+    width: u32,
+    height: u32,
+    bits_per_pixel: u32,
+
+    /// The palette index of every pixel, row by row
+    pixels: Vec<u8>,
+}
+
+impl SpriteImage {
+    /// Return whether the sprite is small enough to be packed in an atlas
+    fn is_small(&self) -> bool {
+        self.width * self.height <= MAX_ATLAS_SPRITE_AREA
+    }
+
+    /// Return the palette index of a pixel
+    fn pixel(&self, x: u32, y: u32) -> u8 {
+        self.pixels[(y * self.width + x) as usize]
+    }
+}
+
+/// Load every PNG file in a directory as a sprite
+fn load_sprites(directory: &str) -> Result<Vec<SpriteImage>, Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed={}", directory);
+
+    let mut paths: Vec<PathBuf> = read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    paths.retain(|path| path.extension().is_some_and(|extension| extension == "png"));
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            println!("cargo:rerun-if-changed={}", path.display());
+            load_sprite(&path)
+                .map_err(|error| format!("Invalid sprite {}: {}", path.display(), error).into())
+        })
+        .collect()
+}
+
+/// Load a sprite from an indexed PNG file
+///
+/// Sprites with a palette of up to two colours use one bit per pixel, and
+/// sprites with a palette of up to four colours use two bits per pixel.
+/// Every pixel must use a colour allowed by the number of bits per pixel.
+fn load_sprite(path: &Path) -> Result<SpriteImage, String> {
+    let name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .filter(|stem| stem.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+        .ok_or("file name must only contain letters, digits and underscores")?
+        .to_owned();
+
+    let file = File::open(path).map_err(|error| error.to_string())?;
+    let mut reader = Decoder::new(file)
+        .read_info()
+        .map_err(|error| error.to_string())?;
+
+    let info = reader.info();
+    if info.color_type != ColorType::Indexed {
+        return Err(format!(
+            "colour type is {:?}, but sprites must be indexed PNGs",
+            info.color_type
+        ));
+    }
+    let colours = info.palette.as_ref().map_or(0, |palette| palette.len() / 3);
+    let bits_per_pixel = match colours {
+        1..=2 => 1,
+        3..=4 => 2,
+        _ => {
+            return Err(format!(
+                "palette has {} colours, but sprites have at most 4",
+                colours
+            ))
+        }
+    };
+    let bit_depth = match info.bit_depth {
+        BitDepth::One => 1,
+        BitDepth::Two => 2,
+        BitDepth::Four => 4,
+        BitDepth::Eight => 8,
+        BitDepth::Sixteen => return Err("bit depth 16 is not supported".to_owned()),
+    };
+    let (width, height) = (info.width, info.height);
+
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader
+        .next_frame(&mut buffer)
+        .map_err(|error| error.to_string())?;
+
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+    for row in buffer.chunks(frame.line_size).take(height as usize) {
+        for x in 0..width {
+            let bit = x * bit_depth;
+            let byte = row[(bit / 8) as usize];
+            let shift = 8 - bit_depth - bit % 8;
+            let index = (byte >> shift) & ((1u16 << bit_depth) - 1) as u8;
+            if index as usize >= colours {
+                return Err(format!(
+                    "pixel ({}, {}) uses colour {}, but the palette only has {} colours",
+                    x,
+                    pixels.len() as u32 / width,
+                    index,
+                    colours
+                ));
+            }
+            pixels.push(index);
+        }
+    }
+
+    Ok(SpriteImage {
+        name,
+        path: path.to_owned(),
+        width,
+        height,
+        bits_per_pixel,
+        pixels,
+    })
+}
+
+/// Encode pixels in WASM-4 sprite format
+///
+/// Pixels are stored row by row without padding, the leftmost pixel in the
+/// most significant bits.
+fn encode_pixels(pixels: &[u8], bits_per_pixel: u32) -> Vec<u8> {
+    let pixels_per_byte = (8 / bits_per_pixel) as usize;
+    pixels
+        .chunks(pixels_per_byte)
+        .map(|chunk| {
+            chunk.iter().enumerate().fold(0, |byte, (i, pixel)| {
+                byte | pixel << (8 - bits_per_pixel as usize * (i + 1))
+            })
+        })
+        .collect()
+}
+
+/// The position of a sprite in an atlas
+struct Placement {
+    x: u32,
+    y: u32,
+}
+
+/// A row of sprites in an atlas
+struct Shelf {
+    y: u32,
+    height: u32,
+    used_width: u32,
+}
+
+/// Pack sprites in an atlas
+///
+/// Sprites are sorted by decreasing height, and each one is placed in the
+/// first row with enough room, or in a new row.
+/// Return the size of the atlas and the position of every sprite.
+fn pack_atlas(sprites: &[&SpriteImage]) -> (u32, u32, Vec<Placement>) {
+    let widest = sprites.iter().map(|sprite| sprite.width).max().unwrap_or(0);
+    let width = MIN_ATLAS_WIDTH.max(widest.div_ceil(8) * 8);
+
+    let mut order: Vec<usize> = (0..sprites.len()).collect();
+    order.sort_by_key(|&i| {
+        let sprite = sprites[i];
+        (Reverse(sprite.height), Reverse(sprite.width), &sprite.name)
+    });
+
+    let mut shelves: Vec<Shelf> = Vec::new();
+    let mut placements: Vec<Option<Placement>> = sprites.iter().map(|_| None).collect();
+    for i in order {
+        let sprite = sprites[i];
+        let fitting = shelves.iter_mut().find(|shelf| {
+            shelf.height >= sprite.height && shelf.used_width + sprite.width <= width
+        });
+        let shelf = match fitting {
+            Some(shelf) => shelf,
+            None => {
+                let y = shelves.last().map_or(0, |shelf| shelf.y + shelf.height);
+                shelves.push(Shelf {
+                    y,
+                    height: sprite.height,
+                    used_width: 0,
+                });
+                shelves.last_mut().unwrap()
+            }
+        };
+        placements[i] = Some(Placement {
+            x: shelf.used_width,
+            y: shelf.y,
+        });
+        shelf.used_width += sprite.width;
+    }
+
+    let height = shelves.last().map_or(0, |shelf| shelf.y + shelf.height);
+    let placements = placements.into_iter().flatten().collect();
+    (width, height, placements)
+}
+
+/// Write the sprite constants
+///
+/// Small sprites with the same number of bits per pixel are packed in a
+/// shared atlas, and their constants are views over it.
+/// Every constant is a `SpriteViewImpl`, named after its file.
+fn write_sprites(sprites: &[SpriteImage]) -> Result<(), Box<dyn std::error::Error>> {
+    let output_directory = PathBuf::from(var("OUT_DIR")?);
+    let mut output = File::create(output_directory.join("sprites.rs"))?;
+
+    let depths: BTreeSet<u32> = sprites.iter().map(|sprite| sprite.bits_per_pixel).collect();
+    for bits_per_pixel in depths {
+        let packed: Vec<&SpriteImage> = sprites
+            .iter()
+            .filter(|sprite| sprite.bits_per_pixel == bits_per_pixel && sprite.is_small())
+            .collect();
+        if packed.is_empty() {
+            continue;
+        }
+
+        let (width, height, placements) = pack_atlas(&packed);
+        let mut pixels = vec![0; (width * height) as usize];
+        for (sprite, placement) in packed.iter().zip(&placements) {
+            for y in 0..sprite.height {
+                for x in 0..sprite.width {
+                    let index = (placement.y + y) * width + placement.x + x;
+                    pixels[index as usize] = sprite.pixel(x, y);
+                }
+            }
+        }
+
+        let atlas = format!("ATLAS_{}BPP", bits_per_pixel);
+        writeln!(
+            output,
+            "/// The atlas of small {}bpp sprites",
+            bits_per_pixel
+        )?;
+        write_sprite_static(&mut output, &atlas, width, height, bits_per_pixel, &pixels)?;
+
+        for (sprite, placement) in packed.iter().zip(&placements) {
+            writeln!(output, "/// The sprite `{}`", file_name(&sprite.path))?;
+            writeln!(
+                output,
+                "pub const {}_SPRITE: SpriteViewImpl<'static> = \
+                 SpriteViewImpl::new(&{}).clip({}, {}, {}, {});",
+                sprite.name.to_uppercase(),
+                atlas,
+                placement.x,
+                placement.y,
+                sprite.width,
+                sprite.height
+            )?;
+        }
+    }
 
-    let mut cargo_instructions = String::default();
-    module.generate_cargo_build_instructions(&mut cargo_instructions)?;
-    println!("{}", cargo_instructions);
+    for sprite in sprites.iter().filter(|sprite| !sprite.is_small()) {
+        let name = sprite.name.to_uppercase();
+        writeln!(output, "/// The pixels of `{}`", file_name(&sprite.path))?;
+        write_sprite_static(
+            &mut output,
+            &name,
+            sprite.width,
+            sprite.height,
+            sprite.bits_per_pixel,
+            &sprite.pixels,
+        )?;
+        writeln!(output, "/// The sprite `{}`", file_name(&sprite.path))?;
+        writeln!(
+            output,
+            "pub const {}_SPRITE: SpriteViewImpl<'static> = SpriteViewImpl::new(&{});",
+            name, name
+        )?;
+    }
 
-    let module = module.parse()?;
-    let mut output_file = open_output_file()?;
-    writeln!(output_file, "{}", module)?;
+    Ok(())
+}
+
+fn write_sprite_static(
+    output: &mut File,
+    name: &str,
+    width: u32,
+    height: u32,
+    bits_per_pixel: u32,
+    pixels: &[u8],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let flags = if bits_per_pixel == 1 {
+        "BLIT_1BPP"
+    } else {
+        "BLIT_2BPP"
+    };
+    writeln!(
+        output,
+        "static {}: Sprite = Sprite::new({}, {}, {}, &{:?});",
+        name,
+        width,
+        height,
+        flags,
+        encode_pixels(pixels, bits_per_pixel)
+    )?;
+    Ok(())
+}
+
+/// The name of the font sprite
+const FONT_NAME: &str = "font4x8";
+
+/// The size of a glyph cell in the font sprite
+const GLYPH_CELL_WIDTH: u32 = 5;
+const GLYPH_CELL_HEIGHT: u32 = 8;
+
+/// The width of glyphs without any pixel, such as space
+const BLANK_GLYPH_WIDTH: u8 = 3;
+
+/// Write the horizontal extent of every glyph in the font sprite
+///
+/// The extent of a glyph spans from its leftmost to its rightmost column with
+/// a pixel different from the background, which is the colour of the top-left
+/// pixel.
+fn write_glyph_widths(font: &SpriteImage) -> Result<(), Box<dyn std::error::Error>> {
+    if font.bits_per_pixel != 1 {
+        return Err(format!("Font {} must have two colours", font.path.display()).into());
+    }
+    if !font.width.is_multiple_of(GLYPH_CELL_WIDTH)
+        || !font.height.is_multiple_of(GLYPH_CELL_HEIGHT)
+    {
+        return Err(format!("Font {} is not a grid of glyphs", font.path.display()).into());
+    }
+
+    let background = font.pixel(0, 0);
+
+    let columns = font.width / GLYPH_CELL_WIDTH;
+    let rows = font.height / GLYPH_CELL_HEIGHT;
+    let glyphs: Vec<(u8, u8)> = (0..rows * columns)
+        .map(|glyph| {
+            let x0 = (glyph % columns) * GLYPH_CELL_WIDTH;
+            let y0 = (glyph / columns) * GLYPH_CELL_HEIGHT;
+            let used: Vec<u8> = (0..GLYPH_CELL_WIDTH)
+                .filter(|dx| {
+                    (0..GLYPH_CELL_HEIGHT).any(|dy| font.pixel(x0 + dx, y0 + dy) != background)
+                })
+                .map(|dx| dx as u8)
+                .collect();
+            match (used.first(), used.last()) {
+                (Some(first), Some(last)) => (*first, last - first + 1),
+                _ => (0, BLANK_GLYPH_WIDTH),
+            }
+        })
+        .collect();
+
+    let output_directory = PathBuf::from(var("OUT_DIR")?);
+    let mut output_file = File::create(output_directory.join("glyphs.rs"))?;
+    writeln!(
+        output_file,
+        "/// The offset of the leftmost column and the width of every glyph"
+    )?;
+    writeln!(
+        output_file,
+        "pub const GLYPHS: [(u8, u8); {}] = {:?};",
+        glyphs.len(),
+        glyphs
+    )?;
 
     Ok(())
 }
 
-fn open_output_file() -> Result<File, Box<dyn std::error::Error>> {
+/// Write the snapshot to embed in the cartridge
+///
+/// The snapshot is read from the file pointed by environment variable
+/// `WASMANOID_SNAPSHOT`, as a hexadecimal dump.
+/// If the variable is not set, an empty snapshot is written.
+fn write_embedded_snapshot() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-env-changed=WASMANOID_SNAPSHOT");
+
+    let bytes = match var("WASMANOID_SNAPSHOT") {
+        Ok(path) => {
+            println!("cargo:rerun-if-changed={}", path);
+            parse_hex_dump(&read_to_string(&path)?)
+                .map_err(|error| format!("Invalid snapshot {}: {}", path, error))?
+        }
+        Err(_) => Vec::new(),
+    };
+
     let output_directory = PathBuf::from(var("OUT_DIR")?);
-    let output_path = output_directory.join("sprites.rs");
-    let output_file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .open(output_path)?;
-    Ok(output_file)
+    write(output_directory.join("snapshot.bin"), bytes)?;
+
+    Ok(())
+}
+
+fn parse_hex_dump(text: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let digits: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    digits
+        .chunks(2)
+        .map(|pair| {
+            if pair.len() != 2 {
+                return Err("odd number of hexadecimal digits".into());
+            }
+            let pair: String = pair.iter().collect();
+            u8::from_str_radix(&pair, 16).map_err(|error| error.into())
+        })
+        .collect()
+}
+
+/// A frame tag read from an Aseprite file
+struct AsepriteTag {
+    name: String,
+    from: u16,
+    to: u16,
+    direction: u8,
+}
+
+/// The animation metadata read from an Aseprite file
+struct AsepriteAnimation {
+    durations: Vec<u16>,
+    tags: Vec<AsepriteTag>,
+}
+
+const ASEPRITE_MAGIC: u16 = 0xa5e0;
+const ASEPRITE_FRAME_MAGIC: u16 = 0xf1fa;
+const ASEPRITE_HEADER_SIZE: usize = 128;
+const ASEPRITE_FRAME_HEADER_SIZE: usize = 16;
+const ASEPRITE_TAGS_CHUNK: u16 = 0x2018;
+
+/// Write the animation metadata of every Aseprite file in a directory
+///
+/// Every file `name.aseprite` generates a constant `NAME` of type
+/// `Sheet` with the duration of each frame and the frame tags.
+fn write_animations(directory: &str) -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed={}", directory);
+
+    let mut paths: Vec<PathBuf> = read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    paths.retain(|path| {
+        path.extension()
+            .is_some_and(|extension| extension == "aseprite")
+    });
+    paths.sort();
+
+    let output_directory = PathBuf::from(var("OUT_DIR")?);
+    let mut output_file = File::create(output_directory.join("animations.rs"))?;
+
+    for path in paths {
+        println!("cargo:rerun-if-changed={}", path.display());
+
+        let animation = parse_aseprite(&read(&path)?)
+            .map_err(|error| format!("Invalid Aseprite file {}: {}", path.display(), error))?;
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| format!("Invalid file name {}", path.display()))?
+            .to_uppercase();
+
+        writeln!(output_file, "/// The animation of `{}`", file_name(&path))?;
+        writeln!(output_file, "pub static {}: Sheet = Sheet {{", name)?;
+        writeln!(output_file, "    durations: &{:?},", animation.durations)?;
+        writeln!(output_file, "    tags: &[")?;
+        for tag in animation.tags {
+            let direction = match tag.direction {
+                0 => "Forward",
+                1 => "Reverse",
+                2 => "PingPong",
+                direction => {
+                    return Err(format!(
+                        "Unsupported direction {} of tag {} in {}",
+                        direction,
+                        tag.name,
+                        path.display()
+                    )
+                    .into())
+                }
+            };
+            writeln!(output_file, "        Tag {{")?;
+            writeln!(output_file, "            name: {:?},", tag.name)?;
+            writeln!(output_file, "            from: {},", tag.from)?;
+            writeln!(output_file, "            to: {},", tag.to)?;
+            writeln!(
+                output_file,
+                "            direction: Direction::{},",
+                direction
+            )?;
+            writeln!(output_file, "        }},")?;
+        }
+        writeln!(output_file, "    ],")?;
+        writeln!(output_file, "}};")?;
+    }
+
+    Ok(())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Parse frame durations and tags from an Aseprite file
+///
+/// See <https://github.com/aseprite/aseprite/blob/main/docs/ase-file-specs.md>.
+fn parse_aseprite(bytes: &[u8]) -> Result<AsepriteAnimation, String> {
+    let word = |offset: usize| -> Result<u16, String> {
+        bytes
+            .get(offset..offset + 2)
+            .map(|word| u16::from_le_bytes([word[0], word[1]]))
+            .ok_or_else(|| "unexpected end of file".to_owned())
+    };
+    let dword = |offset: usize| -> Result<u32, String> {
+        Ok(word(offset)? as u32 | (word(offset + 2)? as u32) << 16)
+    };
+
+    if word(4)? != ASEPRITE_MAGIC {
+        return Err("wrong magic number".to_owned());
+    }
+    let frames = word(6)?;
+    if frames == 0 || frames > u8::MAX as u16 {
+        return Err(format!("unsupported number of frames {}", frames));
+    }
+
+    let mut durations = Vec::new();
+    let mut tags = Vec::new();
+    let mut frame_offset = ASEPRITE_HEADER_SIZE;
+    for _ in 0..frames {
+        let frame_size = dword(frame_offset)? as usize;
+        if word(frame_offset + 4)? != ASEPRITE_FRAME_MAGIC {
+            return Err("wrong frame magic number".to_owned());
+        }
+        durations.push(word(frame_offset + 8)?);
+
+        let chunks = match dword(frame_offset + 12)? {
+            0 => word(frame_offset + 6)? as u32,
+            chunks => chunks,
+        };
+        let mut chunk_offset = frame_offset + ASEPRITE_FRAME_HEADER_SIZE;
+        for _ in 0..chunks {
+            let chunk_size = dword(chunk_offset)? as usize;
+            if word(chunk_offset + 4)? == ASEPRITE_TAGS_CHUNK {
+                let count = word(chunk_offset + 6)?;
+                let mut tag_offset = chunk_offset + 16;
+                for _ in 0..count {
+                    let from = word(tag_offset)?;
+                    let to = word(tag_offset + 2)?;
+                    let direction = bytes
+                        .get(tag_offset + 4)
+                        .copied()
+                        .ok_or_else(|| "unexpected end of file".to_owned())?;
+                    let length = word(tag_offset + 17)? as usize;
+                    let name = bytes
+                        .get(tag_offset + 19..tag_offset + 19 + length)
+                        .ok_or_else(|| "unexpected end of file".to_owned())?;
+                    let name = String::from_utf8_lossy(name).into_owned();
+                    if from > to || to >= frames {
+                        return Err(format!("tag {} has invalid frames", name));
+                    }
+                    tags.push(AsepriteTag {
+                        name,
+                        from,
+                        to,
+                        direction,
+                    });
+                    tag_offset += 19 + length;
+                }
+            }
+            chunk_offset += chunk_size;
+        }
+
+        frame_offset += frame_size;
+    }
+
+    Ok(AsepriteAnimation { durations, tags })
 }
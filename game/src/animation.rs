@@ -0,0 +1,175 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Sprite animations
+//!
+//! The frame durations and tags of every sprite sheet are read from its
+//! Aseprite file at build time, and stored in a [`Sheet`].
+//! An [`Animation`] plays all frames of a sheet or the frames of one of its
+//! tags.
+
+use wasm4fun_time::Ticker;
+
+use crate::FRAMES_PER_SECOND;
+
+/// The direction an animation is played in
+#[allow(unused)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// From the first to the last frame
+    Forward,
+
+    /// From the last to the first frame
+    Reverse,
+
+    /// From the first to the last frame and back
+    PingPong,
+}
+
+/// A named range of frames
+#[derive(Clone, Copy, Debug)]
+pub struct Tag {
+    /// The name of the tag
+    pub name: &'static str,
+
+    /// The first frame of the tag
+    pub from: u8,
+
+    /// The last frame of the tag, inclusive
+    pub to: u8,
+
+    /// The direction the frames are played in
+    pub direction: Direction,
+}
+
+/// The animation metadata of a sprite sheet
+#[derive(Debug)]
+pub struct Sheet {
+    /// The duration of every frame in milliseconds
+    pub durations: &'static [u16],
+
+    /// The tags defined in the sheet
+    pub tags: &'static [Tag],
+}
+
+impl Sheet {
+    /// Return an animation over all frames of the sheet
+    pub const fn animation(&'static self) -> Animation {
+        Animation {
+            durations: self.durations,
+            from: 0,
+            to: self.durations.len() as u8 - 1,
+            direction: Direction::Forward,
+        }
+    }
+
+    /// Return an animation over the frames of a tag
+    #[allow(unused)]
+    pub fn tag(&'static self, name: &str) -> Option<Animation> {
+        self.tags
+            .iter()
+            .find(|tag| tag.name == name)
+            .map(|tag| Animation {
+                durations: self.durations,
+                from: tag.from,
+                to: tag.to,
+                direction: tag.direction,
+            })
+    }
+}
+
+/// A looping animation over a range of frames
+#[derive(Clone, Copy, Debug)]
+pub struct Animation {
+    durations: &'static [u16],
+    from: u8,
+    to: u8,
+    direction: Direction,
+}
+
+impl Animation {
+    /// Return the number of steps in a loop
+    ///
+    /// A ping-pong loop does not repeat its first and last frames.
+    fn steps(&self) -> u8 {
+        let frames = self.to - self.from + 1;
+        match self.direction {
+            Direction::PingPong if frames > 2 => 2 * frames - 2,
+            _ => frames,
+        }
+    }
+
+    /// Return the frame shown at a step
+    fn frame_of_step(&self, step: u8) -> u8 {
+        let frames = self.to - self.from + 1;
+        match self.direction {
+            Direction::Forward => self.from + step,
+            Direction::Reverse => self.to - step,
+            Direction::PingPong if step < frames => self.from + step,
+            Direction::PingPong => self.to - (step - frames + 1),
+        }
+    }
+
+    /// Return the frame shown after some game frames
+    pub fn frame_at(&self, ticks: u64) -> u32 {
+        let steps = self.steps();
+        let duration: u64 = (0..steps)
+            .map(|step| self.durations[self.frame_of_step(step) as usize] as u64)
+            .sum();
+        if duration == 0 {
+            return self.from as u32;
+        }
+
+        let mut elapsed = (ticks * 1000 / FRAMES_PER_SECOND as u64) % duration;
+        for step in 0..steps {
+            let frame = self.frame_of_step(step);
+            let frame_duration = self.durations[frame as usize] as u64;
+            if elapsed < frame_duration {
+                return frame as u32;
+            }
+            elapsed -= frame_duration;
+        }
+        self.to as u32
+    }
+
+    /// Return the frame shown now
+    ///
+    /// All animations are synchronized to the game start.
+    pub fn frame(&self) -> u32 {
+        self.frame_at(Ticker.since_startup())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SHEET: Sheet = Sheet {
+        durations: &[100, 200, 100, 100],
+        tags: &[Tag {
+            name: "bounce",
+            from: 1,
+            to: 3,
+            direction: Direction::PingPong,
+        }],
+    };
+
+    #[test]
+    fn test_animation_follows_frame_durations() {
+        let animation = SHEET.animation();
+        assert_eq!(animation.frame_at(0), 0);
+        assert_eq!(animation.frame_at(6), 1);
+        assert_eq!(animation.frame_at(17), 1);
+        assert_eq!(animation.frame_at(18), 2);
+        assert_eq!(animation.frame_at(24), 3);
+        assert_eq!(animation.frame_at(30), 0);
+
+        let animation = SHEET.tag("bounce").unwrap();
+        let frames: [u32; 5] = [0, 12, 18, 24, 30].map(|ticks| animation.frame_at(ticks));
+        assert_eq!(frames, [1, 2, 3, 2, 1]);
+        assert!(SHEET.tag("missing").is_none());
+    }
+}
@@ -4,35 +4,260 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
 use tinyvec::array_vec;
 use tinyvec::ArrayVec;
 
 use wasm4fun_sound::{DutyCycle, Tone, WaveForm};
 
+use crate::music::{Music, MusicPlayer};
+
 pub const MAX_TONES: usize = 10;
 
+/// A WASM-4 sound channel
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub enum Channel {
+    #[default]
+    Pulse1,
+    Pulse2,
+    Triangle,
+    Noise,
+}
+
+impl Channel {
+    /// The number of channels
+    pub const COUNT: usize = 4;
+
+    #[allow(unused)]
+    pub fn from_index(index: usize) -> Self {
+        match index % Self::COUNT {
+            0 => Channel::Pulse1,
+            1 => Channel::Pulse2,
+            2 => Channel::Triangle,
+            _ => Channel::Noise,
+        }
+    }
+
+    /// Return the index of the channel
+    pub fn index(self) -> usize {
+        match self {
+            Channel::Pulse1 => 0,
+            Channel::Pulse2 => 1,
+            Channel::Triangle => 2,
+            Channel::Noise => 3,
+        }
+    }
+
+    /// Return the wave form played by the channel
+    pub fn wave_form(self) -> WaveForm {
+        match self {
+            Channel::Pulse1 => WaveForm::Pulse1,
+            Channel::Pulse2 => WaveForm::Pulse2,
+            Channel::Triangle => WaveForm::Triangle,
+            Channel::Noise => WaveForm::Noise,
+        }
+    }
+}
+
+/// A duty cycle of the pulse channels
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub enum Duty {
+    /// 12.5% duty cycle
+    #[default]
+    OneEighth,
+
+    /// 25% duty cycle
+    OneQuarter,
+
+    /// 50% duty cycle
+    Half,
+
+    /// 75% duty cycle
+    ThreeQuarters,
+}
+
+impl Duty {
+    /// The number of duty cycles
+    pub const COUNT: usize = 4;
+
+    #[allow(unused)]
+    pub fn from_index(index: usize) -> Self {
+        match index % Self::COUNT {
+            0 => Duty::OneEighth,
+            1 => Duty::OneQuarter,
+            2 => Duty::Half,
+            _ => Duty::ThreeQuarters,
+        }
+    }
+
+    #[allow(unused)]
+    pub fn index(self) -> usize {
+        match self {
+            Duty::OneEighth => 0,
+            Duty::OneQuarter => 1,
+            Duty::Half => 2,
+            Duty::ThreeQuarters => 3,
+        }
+    }
+
+    fn duty_cycle(self) -> DutyCycle {
+        match self {
+            Duty::OneEighth => DutyCycle::OneEight,
+            Duty::OneQuarter => DutyCycle::OneQuarter,
+            Duty::Half => DutyCycle::Half,
+            Duty::ThreeQuarters => DutyCycle::ThreeQuarters,
+        }
+    }
+}
+
+/// The priority of a sound effect
+///
+/// A sound effect cannot interrupt another one with higher priority on the
+/// same channel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub enum Priority {
+    /// Bounces and menu moves
+    #[default]
+    Low,
+
+    /// Destroyed bricks and menu selections
+    Medium,
+
+    /// Caught bonuses
+    High,
+
+    /// Game over and game won
+    Critical,
+}
+
+impl Priority {
+    /// The number of priorities
+    pub const COUNT: usize = 4;
+
+    #[allow(unused)]
+    pub fn from_index(index: usize) -> Self {
+        match index % Self::COUNT {
+            0 => Priority::Low,
+            1 => Priority::Medium,
+            2 => Priority::High,
+            _ => Priority::Critical,
+        }
+    }
+
+    #[allow(unused)]
+    pub fn index(self) -> usize {
+        match self {
+            Priority::Low => 0,
+            Priority::Medium => 1,
+            Priority::High => 2,
+            Priority::Critical => 3,
+        }
+    }
+}
+
+/// A sound effect
+///
+/// The channel and the total duration of the tone are tracked, so that the
+/// music on the same channel can be paused while the effect plays.
+#[derive(Clone, Default)]
+pub struct SoundEffect {
+    tone: Tone,
+    channel: Channel,
+    frames: u8,
+    priority: Priority,
+}
+
+impl SoundEffect {
+    /// Create a sound effect playing a tone for some frames
+    ///
+    /// The tone is set to play on the given channel.
+    pub fn new(channel: Channel, frames: u8, priority: Priority, tone: Tone) -> Self {
+        Self {
+            tone: tone.with_wave_form(channel.wave_form()),
+            channel,
+            frames,
+            priority,
+        }
+    }
+}
+
+/// A queue of sound effects
+///
+/// Each channel plays one sound effect at a time, and a sound effect is
+/// dropped if a more important one is still playing on its channel.
+/// When the queue is full, a new sound effect replaces the least important
+/// one waiting in the queue, or it is dropped.
 pub struct AudioQueue {
-    tones: ArrayVec<[(u64, Tone); MAX_TONES]>,
+    tones: ArrayVec<[(u64, SoundEffect); MAX_TONES]>,
+    channels: [Option<(Priority, u8)>; Channel::COUNT],
+    music: MusicPlayer,
 }
 
 impl AudioQueue {
     pub fn new() -> Self {
         Self {
             tones: array_vec!(),
+            channels: [None; Channel::COUNT],
+            music: MusicPlayer::new(),
+        }
+    }
+
+    pub fn enqueue(&mut self, frame: u64, effect: SoundEffect) {
+        if self.tones.len() < self.tones.capacity() {
+            self.tones.push((frame, effect));
+            return;
+        }
+
+        let least_important = self
+            .tones
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, (_, queued))| queued.priority)
+            .map(|(index, (_, queued))| (index, queued.priority));
+
+        match least_important {
+            Some((index, priority)) if priority < effect.priority => {
+                self.tones[index] = (frame, effect);
+            }
+            _ => {}
         }
     }
 
-    pub fn enqueue(&mut self, frame: u64, tone: Tone) {
-        self.tones.push((frame, tone));
+    pub fn play(&mut self, effect: SoundEffect) {
+        self.enqueue(0, effect);
     }
 
-    pub fn play(&mut self, tone: Tone) {
-        self.enqueue(0, tone);
+    /// Play a music track, or stop the music
+    ///
+    /// The track is not restarted if it is already playing.
+    pub fn play_music(&mut self, music: Option<Music>) {
+        self.music.play(music);
     }
 
     pub fn update(&mut self) {
-        for (_, tone) in self.tones.iter().filter(|(frame, _)| *frame == 0) {
-            tone.play();
+        for channel in self.channels.iter_mut() {
+            *channel = match channel {
+                Some((priority, frames)) if *frames > 1 => Some((*priority, *frames - 1)),
+                _ => None,
+            };
+        }
+
+        for (_, effect) in self.tones.iter().filter(|(frame, _)| *frame == 0) {
+            let channel = &mut self.channels[effect.channel.index()];
+            if let Some((playing, _)) = channel {
+                if *playing > effect.priority {
+                    continue;
+                }
+            }
+
+            effect.tone.play();
+            *channel = Some((effect.priority, effect.frames));
+            self.music.preempt(effect.channel, effect.frames);
         }
 
         self.tones.retain(|(frame, _)| *frame > 0);
@@ -40,71 +265,302 @@ impl AudioQueue {
         for (frame, _) in self.tones.iter_mut() {
             *frame -= 1;
         }
+
+        self.music.update();
     }
 }
 
-pub fn bounce_tone() -> Tone {
-    Tone::new()
-        .with_first_frequency(300)
-        .with_release(10)
-        .with_volume(50)
-        .with_wave_form(WaveForm::Triangle)
+/// A sound effect in the table
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sfx {
+    Bounce,
+    Destroy,
+    Bonus,
+    GameOver,
+    GameWon,
+    GameWonFinal,
+    MenuMove,
+    MenuSelect,
+    ComboBonus,
 }
 
-pub fn destroy_tone() -> Tone {
-    Tone::new()
-        .with_first_frequency(400)
-        .with_second_frequency(700)
-        .with_release(30)
-        .with_volume(50)
-        .with_wave_form(WaveForm::Noise)
-}
+impl Sfx {
+    /// The number of sound effects
+    pub const COUNT: usize = 9;
 
-pub fn bonus_tone() -> Tone {
-    Tone::new()
-        .with_first_frequency(0)
-        .with_second_frequency(1000)
-        .with_attack(12)
-        .with_decay(12)
-        .with_release(12)
-        .with_volume(50)
-        .with_wave_form(WaveForm::Triangle)
-}
+    #[allow(unused)]
+    pub fn from_index(index: usize) -> Self {
+        match index % Self::COUNT {
+            0 => Sfx::Bounce,
+            1 => Sfx::Destroy,
+            2 => Sfx::Bonus,
+            3 => Sfx::GameOver,
+            4 => Sfx::GameWon,
+            5 => Sfx::GameWonFinal,
+            6 => Sfx::MenuMove,
+            7 => Sfx::MenuSelect,
+            _ => Sfx::ComboBonus,
+        }
+    }
+
+    pub fn index(self) -> usize {
+        match self {
+            Sfx::Bounce => 0,
+            Sfx::Destroy => 1,
+            Sfx::Bonus => 2,
+            Sfx::GameOver => 3,
+            Sfx::GameWon => 4,
+            Sfx::GameWonFinal => 5,
+            Sfx::MenuMove => 6,
+            Sfx::MenuSelect => 7,
+            Sfx::ComboBonus => 8,
+        }
+    }
 
-pub fn game_over_tone() -> Tone {
-    Tone::new()
-        .with_first_frequency(300)
-        .with_second_frequency(10)
-        .with_attack(20)
-        .with_decay(70)
-        .with_volume(50)
-        .with_wave_form(WaveForm::Pulse1)
-        .with_duty_cycle(DutyCycle::ThreeQuarters)
+    #[allow(unused)]
+    pub fn name(self) -> &'static str {
+        match self {
+            Sfx::Bounce => "BOUNCE",
+            Sfx::Destroy => "DESTROY",
+            Sfx::Bonus => "BONUS",
+            Sfx::GameOver => "GAME OVER",
+            Sfx::GameWon => "GAME WON",
+            Sfx::GameWonFinal => "GAME WON FINAL",
+            Sfx::MenuMove => "MENU MOVE",
+            Sfx::MenuSelect => "MENU SELECT",
+            Sfx::ComboBonus => "COMBO BONUS",
+        }
+    }
+
+    /// Return the definition of the sound effect from the table
+    pub fn definition(self) -> SfxDefinition {
+        SFX_TABLE[self.index()]
+    }
+
+    /// Return the sound effect from the table
+    pub fn effect(self) -> SoundEffect {
+        self.definition().effect()
+    }
 }
 
-pub fn game_won_tone() -> Tone {
-    Tone::new()
-        .with_first_frequency(500)
-        .with_release(20)
-        .with_volume(50)
-        .with_wave_form(WaveForm::Pulse1)
-        .with_duty_cycle(DutyCycle::ThreeQuarters)
+/// The definition of a sound effect
+///
+/// Durations are expressed in frames.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct SfxDefinition {
+    pub first_frequency: u16,
+    pub second_frequency: u16,
+    pub attack: u8,
+    pub decay: u8,
+    pub sustain: u8,
+    pub release: u8,
+    pub volume: u8,
+    pub channel: Channel,
+    pub duty_cycle: Duty,
+    pub priority: Priority,
 }
 
-pub fn menu_move_tone() -> Tone {
-    Tone::new()
-        .with_first_frequency(1000)
-        .with_second_frequency(300)
-        .with_sustain(4)
-        .with_volume(50)
-        .with_wave_form(WaveForm::Triangle)
+impl SfxDefinition {
+    /// Return the total duration of the sound effect
+    pub fn frames(&self) -> u8 {
+        self.attack
+            .saturating_add(self.decay)
+            .saturating_add(self.sustain)
+            .saturating_add(self.release)
+    }
+
+    /// Return the definition with frequencies raised by some semitones
+    ///
+    /// At most one octave is supported, larger values are clamped.
+    pub fn transposed(mut self, semitones: u8) -> Self {
+        let ratio = SEMITONE_RATIOS[(semitones as usize).min(SEMITONE_RATIOS.len() - 1)];
+        let transpose = |frequency: u16| (frequency as u32 * ratio / 1000).min(u16::MAX as u32);
+        self.first_frequency = transpose(self.first_frequency) as u16;
+        self.second_frequency = transpose(self.second_frequency) as u16;
+        self
+    }
+
+    /// Create the sound effect
+    pub fn effect(&self) -> SoundEffect {
+        let tone = Tone::new()
+            .with_first_frequency(self.first_frequency)
+            .with_second_frequency(self.second_frequency)
+            .with_attack(self.attack)
+            .with_decay(self.decay)
+            .with_sustain(self.sustain)
+            .with_release(self.release)
+            .with_volume(self.volume as u32)
+            .with_duty_cycle(self.duty_cycle.duty_cycle());
+        SoundEffect::new(self.channel, self.frames(), self.priority, tone)
+    }
 }
 
-pub fn menu_select_tone() -> Tone {
-    Tone::new()
-        .with_first_frequency(300)
-        .with_second_frequency(1000)
-        .with_sustain(10)
-        .with_volume(50)
-        .with_wave_form(WaveForm::Triangle)
+/// The frequency ratios of each semitone in an octave, in thousandths
+const SEMITONE_RATIOS: [u32; 13] = [
+    1000, 1059, 1122, 1189, 1260, 1335, 1414, 1498, 1587, 1682, 1782, 1888, 2000,
+];
+
+/// The table of all sound effects, in the order of [`Sfx`]
+pub const SFX_TABLE: [SfxDefinition; Sfx::COUNT] = [
+    // Bounce
+    SfxDefinition {
+        first_frequency: 300,
+        second_frequency: 0,
+        attack: 0,
+        decay: 0,
+        sustain: 0,
+        release: 10,
+        volume: 50,
+        channel: Channel::Triangle,
+        duty_cycle: Duty::OneEighth,
+        priority: Priority::Low,
+    },
+    // Destroy
+    SfxDefinition {
+        first_frequency: 400,
+        second_frequency: 700,
+        attack: 0,
+        decay: 0,
+        sustain: 0,
+        release: 30,
+        volume: 50,
+        channel: Channel::Noise,
+        duty_cycle: Duty::OneEighth,
+        priority: Priority::Medium,
+    },
+    // Bonus
+    SfxDefinition {
+        first_frequency: 0,
+        second_frequency: 1000,
+        attack: 12,
+        decay: 12,
+        sustain: 0,
+        release: 12,
+        volume: 50,
+        channel: Channel::Triangle,
+        duty_cycle: Duty::OneEighth,
+        priority: Priority::High,
+    },
+    // Game over
+    SfxDefinition {
+        first_frequency: 300,
+        second_frequency: 10,
+        attack: 20,
+        decay: 70,
+        sustain: 0,
+        release: 0,
+        volume: 50,
+        channel: Channel::Pulse1,
+        duty_cycle: Duty::ThreeQuarters,
+        priority: Priority::Critical,
+    },
+    // Game won
+    SfxDefinition {
+        first_frequency: 500,
+        second_frequency: 0,
+        attack: 0,
+        decay: 0,
+        sustain: 0,
+        release: 20,
+        volume: 50,
+        channel: Channel::Pulse1,
+        duty_cycle: Duty::ThreeQuarters,
+        priority: Priority::Critical,
+    },
+    // Game won final
+    SfxDefinition {
+        first_frequency: 500,
+        second_frequency: 0,
+        attack: 0,
+        decay: 0,
+        sustain: 0,
+        release: 60,
+        volume: 50,
+        channel: Channel::Pulse1,
+        duty_cycle: Duty::ThreeQuarters,
+        priority: Priority::Critical,
+    },
+    // Menu move
+    SfxDefinition {
+        first_frequency: 1000,
+        second_frequency: 300,
+        attack: 0,
+        decay: 0,
+        sustain: 4,
+        release: 0,
+        volume: 50,
+        channel: Channel::Triangle,
+        duty_cycle: Duty::OneEighth,
+        priority: Priority::Low,
+    },
+    // Menu select
+    SfxDefinition {
+        first_frequency: 300,
+        second_frequency: 1000,
+        attack: 0,
+        decay: 0,
+        sustain: 10,
+        release: 0,
+        volume: 50,
+        channel: Channel::Triangle,
+        duty_cycle: Duty::OneEighth,
+        priority: Priority::Medium,
+    },
+    // Combo bonus
+    SfxDefinition {
+        first_frequency: 600,
+        second_frequency: 1200,
+        attack: 0,
+        decay: 0,
+        sustain: 8,
+        release: 8,
+        volume: 50,
+        channel: Channel::Pulse2,
+        duty_cycle: Duty::Half,
+        priority: Priority::High,
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_queue_replaces_less_important_tones() {
+        let mut queue = AudioQueue::new();
+        for _ in 0..MAX_TONES {
+            queue.enqueue(10, Sfx::Bounce.effect());
+        }
+
+        queue.enqueue(10, Sfx::GameOver.effect());
+        queue.enqueue(10, Sfx::Bounce.effect());
+
+        assert_eq!(queue.tones.len(), MAX_TONES);
+        let critical = queue
+            .tones
+            .iter()
+            .filter(|(_, effect)| effect.priority == Priority::Critical)
+            .count();
+        assert_eq!(critical, 1);
+    }
+
+    #[test]
+    fn test_less_important_tone_does_not_interrupt() {
+        let mut queue = AudioQueue::new();
+        queue.play(Sfx::GameOver.effect());
+        queue.update();
+
+        queue.play(Sfx::GameWon.effect());
+        queue.play(SoundEffect::new(
+            Channel::Pulse1,
+            10,
+            Priority::Low,
+            Tone::new(),
+        ));
+        queue.update();
+
+        let channel = queue.channels[Channel::Pulse1.index()];
+        assert_eq!(channel, Some((Priority::Critical, 20)));
+    }
 }
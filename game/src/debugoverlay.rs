@@ -0,0 +1,151 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! In-game debug overlay and cheat console
+//!
+//! This module is only meant for development and manual testing.
+//!
+//! * Pressing X + Z + Left toggles the overlay, which shows the current frame
+//!   and the state stack, together with whatever the top state wants to show;
+//! * While the overlay is shown, holding X turns the directional buttons into
+//!   cheat keys: Left skips the level, Right grants a bonus and Down toggles an
+//!   invincible floor, while Up opens the sound effects editor.
+//!
+//! WASM-4 does not expose a clock, so the overlay cannot measure the frame
+//! rate or the frame time.
+
+use wasm4fun_graphics::{draw_4x4_text, draw_rect, SCREEN_SIZE};
+use wasm4fun_input::GamePad;
+use wasm4fun_log::debug;
+use wasm4fun_time::Ticker;
+
+use crate::graphics::draw_numbers;
+use crate::statemachine::Machine;
+use crate::theme::Role;
+
+/// The height of a line of text in the overlay
+pub const LINE_HEIGHT: i32 = 6;
+
+/// The width of the overlay panel
+const PANEL_WIDTH: u32 = 80;
+
+/// The longest state name shown in the overlay
+const MAX_NAME_LENGTH: usize = 19;
+
+/// A cheat for quickly testing levels
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cheat {
+    /// Skip to the next level
+    SkipLevel,
+
+    /// Grant a random bonus
+    GrantBonus,
+
+    /// Toggle a floor that bounces the ball back
+    ToggleInvincibleFloor,
+
+    /// Open the sound effects editor
+    OpenSfxEditor,
+}
+
+/// The debug overlay
+pub struct DebugOverlay {
+    visible: bool,
+    chord_pressed: bool,
+}
+
+impl DebugOverlay {
+    /// Create a hidden overlay
+    pub const fn new() -> Self {
+        Self {
+            visible: false,
+            chord_pressed: false,
+        }
+    }
+
+    /// Toggle the overlay or apply a cheat when the corresponding chord is
+    /// pressed
+    pub fn update(&mut self, gamepad: &GamePad, machine: &mut Machine) {
+        let toggle = gamepad.x() && gamepad.z() && gamepad.left();
+
+        let cheat = if self.visible && gamepad.x() && !gamepad.z() {
+            if gamepad.left() {
+                Some(Cheat::SkipLevel)
+            } else if gamepad.right() {
+                Some(Cheat::GrantBonus)
+            } else if gamepad.down() {
+                Some(Cheat::ToggleInvincibleFloor)
+            } else if gamepad.up() {
+                Some(Cheat::OpenSfxEditor)
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        if !self.chord_pressed {
+            if toggle {
+                self.visible = !self.visible;
+                debug!("Debug overlay visible: {}", self.visible);
+            } else if let Some(cheat) = cheat {
+                debug!("Applying cheat {:?}", cheat);
+                machine.cheat(cheat);
+            }
+        }
+
+        self.chord_pressed = toggle || cheat.is_some();
+    }
+
+    /// Draw the overlay on top of the current frame
+    pub fn draw(&self, machine: &Machine) {
+        if !self.visible {
+            return;
+        }
+
+        machine.draw_debug();
+
+        let lines = 1 + machine.state_names().count() as i32;
+        let height = lines * LINE_HEIGHT + 2;
+        let y = SCREEN_SIZE as i32 - height;
+
+        Role::Backdrop.set();
+        draw_rect(0, y, PANEL_WIDTH, height as u32);
+
+        Role::Text.set();
+        let x = 2;
+        let y = y + 2;
+
+        draw_numbers("FRAME", &[Ticker.since_startup() as i32], x, y);
+
+        Role::Accent.set();
+        for (i, name) in machine.state_names().enumerate() {
+            let mut buffer = [0u8; MAX_NAME_LENGTH];
+            let length = name.len().min(MAX_NAME_LENGTH);
+            buffer[..length].copy_from_slice(&name.as_bytes()[..length]);
+            buffer.make_ascii_uppercase();
+            let name = core::str::from_utf8(&buffer[..length]).unwrap_or("?");
+            draw_4x4_text(name, x, y + (1 + i as i32) * LINE_HEIGHT);
+        }
+    }
+}
+
+/// Dump bytes to the debug log as hexadecimal text
+#[cfg(feature = "storage")]
+pub fn dump_hex(bytes: &[u8]) {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    const BYTES_PER_LINE: usize = 32;
+
+    for chunk in bytes.chunks(BYTES_PER_LINE) {
+        let mut line = [0u8; BYTES_PER_LINE * 2];
+        for (i, byte) in chunk.iter().enumerate() {
+            line[2 * i] = DIGITS[(byte >> 4) as usize];
+            line[2 * i + 1] = DIGITS[(byte & 0x0f) as usize];
+        }
+        let line = &line[..chunk.len() * 2];
+        debug!("{}", unsafe { core::str::from_utf8_unchecked(line) });
+    }
+}
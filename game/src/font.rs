@@ -0,0 +1,220 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Proportional text with the 5×8 font
+//!
+//! The font sprite is a grid of 5×8 cells, one for each printable ASCII
+//! character.
+//! The horizontal extent of every glyph is computed from the sprite at build
+//! time, so that text is drawn with proportional spacing.
+//! Characters missing from the font are drawn as a question mark.
+
+use wasm4fun_graphics::SCREEN_SIZE;
+
+use crate::assets::FONT4X8_SPRITE;
+
+mod glyphs {
+    include!(concat!(env!("OUT_DIR"), "/glyphs.rs"));
+}
+
+use glyphs::GLYPHS;
+
+/// The size of a glyph cell in the font sprite
+const CELL_WIDTH: u32 = 5;
+const CELL_HEIGHT: u32 = 8;
+
+/// The number of glyph cells in each row of the font sprite
+const COLUMNS: u32 = FONT4X8_SPRITE.width() / CELL_WIDTH;
+
+/// The first character in the font sprite
+const FIRST_CHARACTER: u32 = ' ' as u32;
+
+/// The character drawn in place of characters missing from the font
+const REPLACEMENT_CHARACTER: char = '?';
+
+/// The horizontal space between glyphs
+const SPACING: u32 = 1;
+
+/// The vertical distance between lines of wrapped text
+pub const LINE_HEIGHT: i32 = 10;
+
+/// The horizontal alignment of text within a box
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Alignment {
+    /// Text starts at the left edge of the box
+    Left,
+
+    /// Text is centered in the box
+    Center,
+
+    /// Text ends at the right edge of the box
+    Right,
+}
+
+/// Return the index of the glyph for a character
+fn glyph_index(c: char) -> usize {
+    match (c as u32).checked_sub(FIRST_CHARACTER) {
+        Some(index) if (index as usize) < GLYPHS.len() => index as usize,
+        _ => glyph_index(REPLACEMENT_CHARACTER),
+    }
+}
+
+/// Return the width of a glyph, without spacing
+fn glyph_width(c: char) -> u32 {
+    GLYPHS[glyph_index(c)].1 as u32
+}
+
+/// Return the horizontal distance from a glyph to the next one
+fn glyph_advance(c: char) -> u32 {
+    glyph_width(c) + SPACING
+}
+
+/// Return the width of a text
+pub fn text_width(s: impl AsRef<str>) -> u32 {
+    let width: u32 = s.as_ref().chars().map(glyph_advance).sum();
+    width.saturating_sub(SPACING)
+}
+
+/// Draw text with the 5×8 font using the current colours
+pub fn draw_5x8_text(s: impl AsRef<str>, x: i32, y: i32) {
+    let mut x = x;
+    for c in s.as_ref().chars() {
+        let index = glyph_index(c) as u32;
+        let (offset, width) = GLYPHS[index as usize];
+        let src_x = (index % COLUMNS) * CELL_WIDTH + offset as u32;
+        let src_y = (index / COLUMNS) * CELL_HEIGHT;
+
+        FONT4X8_SPRITE
+            .clip(src_x, src_y, width as u32, CELL_HEIGHT)
+            .blit(x, y);
+
+        x += (width as u32 + SPACING) as i32;
+    }
+}
+
+/// Draw text aligned within a box of the given width
+pub fn draw_aligned_5x8_text(s: impl AsRef<str>, x: i32, y: i32, width: u32, alignment: Alignment) {
+    let s = s.as_ref();
+    let free_space = width as i32 - text_width(s) as i32;
+    let x = match alignment {
+        Alignment::Left => x,
+        Alignment::Center => x + free_space / 2,
+        Alignment::Right => x + free_space,
+    };
+    draw_5x8_text(s, x, y);
+}
+
+/// Draw text centered horizontally on the screen
+pub fn draw_centered_5x8_text(s: impl AsRef<str>, y: i32) {
+    draw_aligned_5x8_text(s, 0, y, SCREEN_SIZE, Alignment::Center);
+}
+
+/// Draw text wrapped within a box of the given width
+///
+/// Return the number of lines drawn.
+pub fn draw_wrapped_5x8_text(
+    s: impl AsRef<str>,
+    x: i32,
+    y: i32,
+    width: u32,
+    alignment: Alignment,
+) -> i32 {
+    let mut lines = 0;
+    for line in wrap(s.as_ref(), width) {
+        draw_aligned_5x8_text(line, x, y + lines * LINE_HEIGHT, width, alignment);
+        lines += 1;
+    }
+    lines
+}
+
+/// Split text into lines fitting within a width
+///
+/// Lines are broken at newlines and at spaces, and words longer than the
+/// width are broken at any character.
+pub fn wrap(s: &str, width: u32) -> WrappedLines<'_> {
+    WrappedLines { text: s, width }
+}
+
+/// An iterator over the lines of wrapped text
+pub struct WrappedLines<'a> {
+    text: &'a str,
+    width: u32,
+}
+
+impl<'a> Iterator for WrappedLines<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        if self.text.is_empty() {
+            return None;
+        }
+
+        let text = self.text;
+        let mut line_width = 0;
+        let mut last_space = None;
+
+        for (i, c) in text.char_indices() {
+            if c == '\n' {
+                self.text = &text[i + 1..];
+                return Some(&text[..i]);
+            }
+
+            if c == ' ' {
+                last_space = Some(i);
+            }
+
+            if i > 0 && line_width + glyph_width(c) > self.width {
+                let (end, next) = match (c, last_space) {
+                    (' ', _) => (i, i + 1),
+                    (_, Some(space)) => (space, space + 1),
+                    (_, None) => (i, i),
+                };
+                self.text = text[next..].trim_start_matches(' ');
+                return Some(&text[..end]);
+            }
+
+            line_width += glyph_advance(c);
+        }
+
+        self.text = "";
+        Some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_characters_are_replaced() {
+        assert_eq!(glyph_index('\t'), glyph_index('?'));
+        assert_eq!(glyph_index('é'), glyph_index('?'));
+        assert_eq!(glyph_index(' '), 0);
+        assert_eq!(text_width(""), 0);
+        assert_eq!(text_width("\u{0}"), glyph_width('?'));
+    }
+
+    #[test]
+    fn test_text_is_wrapped_at_spaces() {
+        let text = "FOR WASM-4 JAM";
+        let width = text_width("FOR WASM-4");
+        let mut lines = wrap(text, width);
+        assert_eq!(lines.next(), Some("FOR WASM-4"));
+        assert_eq!(lines.next(), Some("JAM"));
+        assert_eq!(lines.next(), None);
+
+        let mut lines = wrap("A\nB", 100);
+        assert_eq!(lines.next(), Some("A"));
+        assert_eq!(lines.next(), Some("B"));
+        assert_eq!(lines.next(), None);
+
+        let width = text_width("ABC");
+        let mut lines = wrap("ABCDEF", width);
+        assert_eq!(lines.next(), Some("ABC"));
+        assert_eq!(lines.next(), Some("DEF"));
+        assert_eq!(lines.next(), None);
+    }
+}
@@ -0,0 +1,196 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use wasm4fun_graphics::{draw_point, draw_rect};
+use wasm4fun_time::Ticker;
+
+use crate::theme::Role;
+use crate::FRAMES_PER_SECOND;
+
+/// An animated layer of a background
+///
+/// Velocities are in pixels per second.
+/// Layers hold no state, their position is computed from the time since the
+/// game started, so drawing them costs a few draw calls and no memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layer {
+    /// A tiled grid of dots
+    Lattice { spacing: u8, velocity: (i8, i8) },
+
+    /// Stars scattered over the area and drifting down
+    Stars { count: u8, seed: u32, speed: u8 },
+
+    /// A checkerboard of squares
+    Checker { size: u8, velocity: (i8, i8) },
+}
+
+/// An area of the screen
+#[derive(Clone, Copy, Debug)]
+struct Area {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+impl Area {
+    /// Fill the intersection of a rectangle with the area
+    fn fill(&self, x: i32, y: i32, width: i32, height: i32) {
+        let left = x.max(self.x);
+        let top = y.max(self.y);
+        let right = (x + width).min(self.x + self.width);
+        let bottom = (y + height).min(self.y + self.height);
+        if left < right && top < bottom {
+            draw_rect(left, top, (right - left) as u32, (bottom - top) as u32);
+        }
+    }
+}
+
+impl Layer {
+    fn draw(&self, area: Area, ticks: u64) {
+        match *self {
+            Layer::Lattice { spacing, velocity } => {
+                let spacing = spacing as i32;
+                let (dx, dy) = scroll(ticks, velocity, spacing);
+                Role::Muted.set();
+                for y in (dy..area.height).step_by(spacing as usize) {
+                    for x in (dx..area.width).step_by(spacing as usize) {
+                        draw_point(area.x + x, area.y + y);
+                    }
+                }
+            }
+            Layer::Stars { count, seed, speed } => {
+                let (_, dy) = scroll(ticks, (0, speed as i8), area.height);
+                Role::Muted.set();
+                for star in 0..count as u32 {
+                    let (x, y) = star_position(seed, star, area.width, area.height);
+                    let y = (y + dy) % area.height;
+                    draw_point(area.x + x, area.y + y);
+                }
+            }
+            Layer::Checker { size, velocity } => {
+                let size = size as i32;
+                let (dx, dy) = scroll(ticks, velocity, 2 * size);
+                Role::MutedFill.set();
+                for (row, y) in (dy - 2 * size..area.height)
+                    .step_by(size as usize)
+                    .enumerate()
+                {
+                    let shift = (row as i32 % 2) * size;
+                    for x in (dx - 2 * size + shift..area.width).step_by(2 * size as usize) {
+                        area.fill(area.x + x, area.y + y, size, size);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A background made of layers drawn from back to front
+///
+/// Layers moving at different speeds give a sense of depth.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Background {
+    layers: &'static [Layer],
+}
+
+impl Background {
+    /// A flat backdrop
+    pub const PLAIN: Background = Background::new(&[]);
+
+    /// Two layers of stars, the nearer one drifting faster
+    pub const STARFIELD: Background = Background::new(&[
+        Layer::Stars {
+            count: 24,
+            seed: 1,
+            speed: 3,
+        },
+        Layer::Stars {
+            count: 12,
+            seed: 2,
+            speed: 8,
+        },
+    ]);
+
+    /// Two grids of dots sliding apart
+    pub const LATTICE: Background = Background::new(&[
+        Layer::Lattice {
+            spacing: 16,
+            velocity: (2, 1),
+        },
+        Layer::Lattice {
+            spacing: 12,
+            velocity: (-4, 2),
+        },
+    ]);
+
+    /// A checkerboard scrolling diagonally
+    pub const CHECKER: Background = Background::new(&[Layer::Checker {
+        size: 12,
+        velocity: (3, 3),
+    }]);
+
+    /// Create a new background from its layers
+    pub const fn new(layers: &'static [Layer]) -> Self {
+        Self { layers }
+    }
+
+    /// Draw the background over an area of the screen
+    ///
+    /// The area must be cleared beforehand.
+    pub fn draw(&self, x: i32, y: i32, width: u32, height: u32) {
+        let area = Area {
+            x,
+            y,
+            width: width as i32,
+            height: height as i32,
+        };
+        let ticks = Ticker.since_startup();
+        for layer in self.layers {
+            layer.draw(area, ticks);
+        }
+    }
+}
+
+/// Return the offset of a pattern repeating every period pixels
+fn scroll(ticks: u64, velocity: (i8, i8), period: i32) -> (i32, i32) {
+    let offset = |speed: i8| {
+        let distance = ticks as i64 * speed as i64 / FRAMES_PER_SECOND as i64;
+        distance.rem_euclid(period as i64) as i32
+    };
+    (offset(velocity.0), offset(velocity.1))
+}
+
+/// Return the initial position of a star
+///
+/// Positions are scattered by hashing the star index.
+fn star_position(seed: u32, star: u32, width: i32, height: i32) -> (i32, i32) {
+    let hash = (star.wrapping_add(seed.wrapping_mul(0x9e37_79b9)))
+        .wrapping_mul(0x85eb_ca6b)
+        .rotate_left(13)
+        .wrapping_mul(0xc2b2_ae35);
+    let x = (hash & 0xffff) as i32 % width;
+    let y = (hash >> 16) as i32 % height;
+    (x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layers_scroll_within_their_period() {
+        assert_eq!(scroll(0, (3, -3), 24), (0, 0));
+        assert_eq!(scroll(60, (3, -3), 24), (3, 21));
+        assert_eq!(scroll(600, (3, -3), 24), (6, 18));
+
+        for star in 0..32 {
+            let (x, y) = star_position(1, star, 144, 144);
+            assert!((0..144).contains(&x) && (0..144).contains(&y));
+        }
+        assert_ne!(star_position(1, 0, 144, 144), star_position(2, 0, 144, 144));
+    }
+}
@@ -7,32 +7,36 @@
 #[cfg(target_family = "wasm")]
 use micromath::F32Ext;
 
-use wasm4fun_graphics::{set_drawing_colors, Rotation, SCREEN_SIZE};
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use wasm4fun_graphics::Rotation;
 use wasm4fun_log::debug;
-use wasm4fun_time::Ticker;
 
-use crate::assets::BALL_SPRITE;
-use crate::audio::{bounce_tone, destroy_tone, AudioQueue};
+use crate::assets::{animations, BALL_SPRITE};
+use crate::audio::{AudioQueue, Sfx};
 use crate::math::{
     intersects_horizontal_line, intersects_horizontal_segment, intersects_vertical_line,
-    intersects_vertical_segment, normalize_vector,
+    intersects_vertical_segment, normalize_vector, Contact,
 };
+use crate::theme::Role;
 use crate::ELAPSED_TIME_IN_SECONDS;
 
-use super::{Bar, Bonus, Brick};
+use super::{Bar, Brick, Camera, Combo, Playfield};
 
-const LEFT_WALL: i32 = 8;
-const TOP_WALL: i32 = 16;
-const BOARD_WIDTH: i32 = 144;
-const BAR_Y: i32 = SCREEN_SIZE as i32 - 30;
-const INITIAL_BALL_SPEED: f32 = 80.0;
 const INITIAL_BALL_STRENGTH: u8 = 1;
 const BALL_RADIUS: u32 = 4;
-const BRICK_WIDTH: u32 = 24;
-const BRICK_HEIGHT: u32 = 8;
+
+/// The height of the ball above the bar when served
+const SERVE_HEIGHT: f32 = 10.0;
+
+/// The distance below the board past which the ball is lost
+const LOST_DISTANCE: f32 = 16.0;
 const MINIMAL_VERTICAL_VELOCITY: f32 = 0.1;
 const MAX_BOUNCE_CALLBACK: u8 = 10;
 
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
 pub struct Ball {
     coordinates: (f32, f32),
     speed: f32,
@@ -40,24 +44,64 @@ pub struct Ball {
     strength: u8,
 
     bounce_callback: u8,
+    contact: Option<Contact>,
 }
 
 impl Ball {
-    pub fn new(initial_ball_unit_velocity: (f32, f32)) -> Self {
+    pub fn new(
+        initial_ball_unit_velocity: (f32, f32),
+        initial_ball_speed: f32,
+        playfield: &Playfield,
+    ) -> Self {
         Self {
             coordinates: (
-                (SCREEN_SIZE - BALL_RADIUS) as f32 / 2.0,
-                BAR_Y as f32 - 10.0,
+                playfield.width() as f32 / 2.0,
+                playfield.bar_y() as f32 - SERVE_HEIGHT,
             ),
-            speed: INITIAL_BALL_SPEED,
+            speed: initial_ball_speed,
             unit_velocity: initial_ball_unit_velocity,
             strength: INITIAL_BALL_STRENGTH,
             bounce_callback: 0,
+            contact: None,
         }
     }
 
-    pub fn is_lost(&self) -> bool {
-        self.coordinates.1 > SCREEN_SIZE as f32
+    /// Move the ball back to its initial position
+    ///
+    /// Speed and strength of the ball are preserved.
+    pub fn reset(&mut self, initial_ball_unit_velocity: (f32, f32), playfield: &Playfield) {
+        *self = Self {
+            strength: self.strength,
+            ..Self::new(initial_ball_unit_velocity, self.speed, playfield)
+        };
+    }
+
+    pub fn is_lost(&self, playfield: &Playfield) -> bool {
+        self.coordinates.1 > playfield.height() as f32 + LOST_DISTANCE
+    }
+
+    pub fn coordinates(&self) -> (f32, f32) {
+        self.coordinates
+    }
+
+    pub fn set_coordinates(&mut self, coordinates: (f32, f32)) {
+        self.coordinates = coordinates;
+    }
+
+    pub fn velocity(&self) -> (f32, f32) {
+        (
+            self.speed * self.unit_velocity.0,
+            self.speed * self.unit_velocity.1,
+        )
+    }
+
+    pub fn set_unit_velocity(&mut self, unit_velocity: (f32, f32)) {
+        self.unit_velocity = normalize_vector(unit_velocity);
+    }
+
+    /// Return the last point where the ball bounced
+    pub fn last_contact(&self) -> Option<Contact> {
+        self.contact
     }
 
     pub fn strength(&self) -> u8 {
@@ -90,11 +134,12 @@ impl Ball {
         *y += dy;
     }
 
-    pub fn draw(&self) {
+    pub fn draw(&self, camera: &Camera, playfield: &Playfield) {
         let (x, y) = self.coordinates;
-        let (x, y) = (x as i32 + LEFT_WALL, y as i32 + TOP_WALL);
+        let (x, y) = playfield.to_screen(x as i32, y as i32);
+        let (x, y) = camera.translate(x, y);
 
-        let src_x = (8.0 * (Ticker.within_second() as f32 / 60.0)) as u32 * 8;
+        let src_x = animations::BALL.animation().frame() * 8;
         let src_y;
         let rotation;
         if self.unit_velocity.0.abs() > self.unit_velocity.1.abs() + 0.5 {
@@ -135,27 +180,48 @@ impl Ball {
             }
         }
 
-        set_drawing_colors(0x1230);
+        Role::Sprite.set();
         BALL_SPRITE
             .clip(src_x as u32, src_y, 8, 8)
             .rotate(rotation)
             .blit(x - BALL_RADIUS as i32, y - BALL_RADIUS as i32);
     }
 
+    /// Bounce the ball upward when it reaches the bottom of the board
+    #[cfg(feature = "debug")]
+    pub fn bounce_on_floor(&mut self, playfield: &Playfield) {
+        let floor = playfield.height() as f32 - BALL_RADIUS as f32;
+        if self.unit_velocity.1 > 0.0 && self.coordinates.1 >= floor {
+            debug!("Bounce upward from floor");
+            let point = (self.coordinates.0, floor + BALL_RADIUS as f32);
+            self.contact = Some(Contact::new(point, (0.0, -1.0)));
+            self.unit_velocity.1 *= -1.0;
+        }
+    }
+
+    /// Bounce the ball on the bar, bricks and walls
+    ///
+    /// Brick hits extend the combo, which raises the pitch of the hit tone
+    /// and multiplies the score, while bouncing on the bar ends it.
+    ///
+    /// Return the points scored, and the board coordinates of the center of
+    /// the last destroyed brick, if any.
     pub fn handle_collisions(
         &mut self,
+        playfield: &Playfield,
         bar: &Bar,
         bricks: &mut [Brick],
+        combo: &mut Combo,
         audio_queue: &mut AudioQueue,
-    ) -> (u32, Option<Bonus>) {
+    ) -> (u32, Option<(u8, u8)>) {
         let score_and_bonus = if self.bounce_callback == 0 {
-            self.handle_collisions_with_bar(bar, audio_queue);
-            self.handle_collisions_with_bricks(bricks, audio_queue)
+            self.handle_collisions_with_bar(playfield, bar, combo, audio_queue);
+            self.handle_collisions_with_bricks(bricks, combo, audio_queue)
         } else {
             (0, None)
         };
 
-        self.handle_collisions_with_walls(audio_queue);
+        self.handle_collisions_with_walls(playfield, audio_queue);
 
         if self.bounce_callback > 0 {
             self.bounce_callback -= 1;
@@ -167,33 +233,35 @@ impl Ball {
     fn handle_collisions_with_bricks(
         &mut self,
         bricks: &mut [Brick],
+        combo: &mut Combo,
         audio_queue: &mut AudioQueue,
-    ) -> (u32, Option<Bonus>) {
+    ) -> (u32, Option<(u8, u8)>) {
         let (bx, by) = (self.coordinates.0 as i32, self.coordinates.1 as i32);
 
         let mut score = 0;
         let mut bonus = None;
 
-        for (_i, (x, y, health)) in bricks.iter_mut().enumerate() {
-            let left = *x as i32;
-            let right = *x as i32 + BRICK_WIDTH as i32;
-            let top = *y as i32;
-            let bottom = *y as i32 + BRICK_HEIGHT as i32;
+        for brick in bricks.iter_mut().filter(|brick| brick.is_solid()) {
+            let (x, y) = brick.position();
+            let left = x as i32;
+            let right = x as i32 + Brick::WIDTH as i32;
+            let top = y as i32;
+            let bottom = y as i32 + Brick::HEIGHT as i32;
 
-            let health = intersects_horizontal_segment((bx, by), BALL_RADIUS, left, right, top)
+            let hit = intersects_horizontal_segment((bx, by), BALL_RADIUS, left, right, top)
                 .map(|ratio| {
                     debug!("Bounce upward with ratio {}", ratio);
+                    let point = (left as f32 + ratio * Brick::WIDTH as f32, top as f32);
+                    self.contact = Some(Contact::new(point, (0.0, -1.0)));
                     self.unit_velocity.1 *= -1.0;
-                    *health -= self.strength.min(*health);
-                    *health
                 })
                 .or_else(|| {
                     intersects_horizontal_segment((bx, by), BALL_RADIUS, left, right, bottom).map(
                         |ratio| {
                             debug!("Bounce downward with ratio {}", ratio);
+                            let point = (left as f32 + ratio * Brick::WIDTH as f32, bottom as f32);
+                            self.contact = Some(Contact::new(point, (0.0, 1.0)));
                             self.unit_velocity.1 *= -1.0;
-                            *health -= self.strength.min(*health);
-                            *health
                         },
                     )
                 })
@@ -201,9 +269,9 @@ impl Ball {
                     intersects_vertical_segment((bx, by), BALL_RADIUS, top, bottom, left).map(
                         |ratio| {
                             debug!("Bounce leftward with ratio {}", ratio);
+                            let point = (left as f32, top as f32 + ratio * Brick::HEIGHT as f32);
+                            self.contact = Some(Contact::new(point, (-1.0, 0.0)));
                             self.unit_velocity.0 *= -1.0;
-                            *health -= self.strength.min(*health);
-                            *health
                         },
                     )
                 })
@@ -211,65 +279,90 @@ impl Ball {
                     intersects_vertical_segment((bx, by), BALL_RADIUS, top, bottom, right).map(
                         |ratio| {
                             debug!("Bounce rightward with ratio {}", ratio);
+                            let point = (right as f32, top as f32 + ratio * Brick::HEIGHT as f32);
+                            self.contact = Some(Contact::new(point, (1.0, 0.0)));
                             self.unit_velocity.0 *= -1.0;
-                            *health -= self.strength.min(*health);
-                            *health
                         },
                     )
-                });
+                })
+                .is_some();
+
+            if !hit {
+                continue;
+            }
 
-            match health {
+            self.bounce_callback = MAX_BOUNCE_CALLBACK;
+            match brick.hit(self.strength) {
                 Some(0) => {
-                    self.bounce_callback = MAX_BOUNCE_CALLBACK;
                     debug!("Brick destroyed");
-                    audio_queue.play(destroy_tone());
+                    combo.hit();
+                    let definition = Sfx::Destroy.definition().transposed(combo.semitones());
+                    audio_queue.play(definition.effect());
 
-                    score += 100;
-                    bonus = Some((*x + BRICK_WIDTH as u8 / 2, *y + BRICK_HEIGHT as u8 / 2));
+                    score += 100 * combo.multiplier();
+                    bonus = Some((x + Brick::WIDTH as u8 / 2, y + Brick::HEIGHT as u8 / 2));
                 }
                 Some(_) => {
-                    self.bounce_callback = MAX_BOUNCE_CALLBACK;
-                    audio_queue.play(bounce_tone());
+                    combo.hit();
+                    let definition = Sfx::Bounce.definition().transposed(combo.semitones());
+                    audio_queue.play(definition.effect());
 
-                    score += 10;
+                    score += 10 * combo.multiplier();
+                }
+                None => {
+                    debug!("Indestructible brick hit");
+                    audio_queue.play(Sfx::Bounce.effect());
                 }
-                None => {}
             }
         }
 
         (score, bonus)
     }
 
-    fn handle_collisions_with_walls(&mut self, audio_queue: &mut AudioQueue) {
+    fn handle_collisions_with_walls(
+        &mut self,
+        playfield: &Playfield,
+        audio_queue: &mut AudioQueue,
+    ) {
         let (bx, by) = (self.coordinates.0 as i32, self.coordinates.1 as i32);
+        let right_wall = playfield.width() as i32;
 
         if self.unit_velocity.1 < 0.0 && intersects_horizontal_line((bx, by), BALL_RADIUS, 0) {
             // Bounce downward
             debug!("Bounce downward from wall");
             debug!("Ball coordinates were {}x{}", bx, by);
-            audio_queue.play(bounce_tone());
+            self.contact = Some(Contact::new((bx as f32, 0.0), (0.0, 1.0)));
+            audio_queue.play(Sfx::Bounce.effect());
             self.unit_velocity.1 *= -1.0;
         } else if self.unit_velocity.0 < 0.0 && intersects_vertical_line((bx, by), BALL_RADIUS, 0) {
             // Bounce leftward
             debug!("Bounce leftward from wall");
             debug!("Ball coordinates were {}x{}", bx, by);
-            audio_queue.play(bounce_tone());
+            self.contact = Some(Contact::new((0.0, by as f32), (1.0, 0.0)));
+            audio_queue.play(Sfx::Bounce.effect());
             self.unit_velocity.0 *= -1.0;
         } else if self.unit_velocity.0 > 0.0
-            && intersects_vertical_line((bx, by), BALL_RADIUS, BOARD_WIDTH as i32)
+            && intersects_vertical_line((bx, by), BALL_RADIUS, right_wall)
         {
             // Bounce rightward
             debug!("Bounce rightward from wall");
             debug!("Ball coordinates were {}x{}", bx, by);
-            audio_queue.play(bounce_tone());
+            self.contact = Some(Contact::new((right_wall as f32, by as f32), (-1.0, 0.0)));
+            audio_queue.play(Sfx::Bounce.effect());
             self.unit_velocity.0 *= -1.0;
         }
     }
 
-    fn handle_collisions_with_bar(&mut self, bar: &Bar, audio_queue: &mut AudioQueue) {
+    fn handle_collisions_with_bar(
+        &mut self,
+        playfield: &Playfield,
+        bar: &Bar,
+        combo: &mut Combo,
+        audio_queue: &mut AudioQueue,
+    ) {
         let (bx, by) = (self.coordinates.0 as i32, self.coordinates.1 as i32);
 
-        let (x, y) = (bar.position() as i32, BAR_Y);
+        let (x, y) = (bar.position() as i32, playfield.bar_y());
         let left = x as i32;
         let right = x as i32 + bar.width() as i32;
         let center_x = x as i32 + bar.width() as i32 / 2;
@@ -282,6 +375,7 @@ impl Ball {
                 debug!("Bounce upward from bar with ratio {}", ratio);
                 let hit_x = left as f32 + (bar.width() as f32 * (1.0 - ratio));
                 let hit_y = top as f32;
+                self.contact = Some(Contact::new((hit_x, hit_y), (0.0, -1.0)));
                 self.unit_velocity.0 = hit_x - center_x as f32;
                 self.unit_velocity.1 = hit_y - center_y as f32;
             });
@@ -319,7 +413,8 @@ impl Ball {
 
         if bounced.is_some() {
             self.bounce_callback = MAX_BOUNCE_CALLBACK;
-            audio_queue.play(bounce_tone());
+            combo.end();
+            audio_queue.play(Sfx::Bounce.effect());
         }
     }
 }
@@ -4,19 +4,25 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use wasm4fun_graphics::{set_drawing_colors, SCREEN_SIZE};
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
 use wasm4fun_input::GamePad;
 
 use crate::assets::BAR_SPRITE;
+use crate::theme::Role;
 use crate::ELAPSED_TIME_IN_SECONDS;
 
-const LEFT_WALL: i32 = 8;
-const TOP_WALL: i32 = 16;
-const BOARD_WIDTH: i32 = 144;
+use super::{Camera, Playfield};
+
 const INITIAL_BAR_SECTIONS: u32 = 1;
-const BAR_Y: i32 = SCREEN_SIZE as i32 - 30;
 const INITIAL_BAR_SPEED: f32 = 100.0;
 
+/// The number of transparent rows above the bar in its sprite
+const SPRITE_TOP_MARGIN: i32 = 2;
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
 pub struct Bar {
     position: f32,
     sections: u32,
@@ -24,12 +30,27 @@ pub struct Bar {
 }
 
 impl Bar {
-    pub fn new() -> Self {
-        Self {
-            position: (SCREEN_SIZE - (INITIAL_BAR_SECTIONS + 2) * 8) as f32 / 2.0,
+    /// The maximal number of middle sections
+    pub const MAX_SECTIONS: u32 = 4;
+
+    /// The width of a bar with all its sections
+    pub const MAX_WIDTH: u32 = (Self::MAX_SECTIONS + 2) * 8;
+
+    pub fn new(playfield: &Playfield) -> Self {
+        let mut bar = Self {
+            position: 0.0,
             sections: INITIAL_BAR_SECTIONS,
             speed: INITIAL_BAR_SPEED,
-        }
+        };
+        bar.recenter(playfield);
+        bar
+    }
+
+    /// Move the bar back to the center of the board
+    ///
+    /// Upgrades to the bar are preserved.
+    pub fn recenter(&mut self, playfield: &Playfield) {
+        self.position = playfield.width().saturating_sub(self.width()) as f32 / 2.0;
     }
 
     pub fn width(&self) -> u32 {
@@ -40,18 +61,17 @@ impl Bar {
         6
     }
 
-    pub fn update_position(&mut self, gamepad: &GamePad) {
+    pub fn update_position(&mut self, gamepad: &GamePad, playfield: &Playfield) {
         if gamepad.left() {
             self.position -= self.speed * ELAPSED_TIME_IN_SECONDS;
         } else if gamepad.right() {
             self.position += self.speed * ELAPSED_TIME_IN_SECONDS;
         }
-        self.position = self.position.clamp(
-            LEFT_WALL as f32,
-            (BOARD_WIDTH - (self.sections + 2) as i32 * 8 + LEFT_WALL) as f32,
-        );
+        let max_position = playfield.width().saturating_sub(self.width());
+        self.position = self.position.clamp(0.0, max_position as f32);
     }
 
+    /// Return the horizontal board coordinate of the left end of the bar
     pub fn position(&self) -> f32 {
         self.position
     }
@@ -72,11 +92,12 @@ impl Bar {
         self.speed *= 1.1;
     }
 
-    pub fn draw(&self) {
-        let bar_x = self.position as i32;
-        let bar_y = BAR_Y + TOP_WALL - 2;
+    pub fn draw(&self, camera: &Camera, playfield: &Playfield) {
+        let y = playfield.bar_y() - SPRITE_TOP_MARGIN;
+        let (x, y) = playfield.to_screen(self.position as i32, y);
+        let (bar_x, bar_y) = camera.translate(x, y);
 
-        set_drawing_colors(0x1230);
+        Role::Sprite.set();
 
         BAR_SPRITE.clip(0, 0, 8, 8).blit(bar_x, bar_y);
         for i in 0..self.sections {
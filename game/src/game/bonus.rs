@@ -4,4 +4,5 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+/// A falling bonus, as the board coordinates of its top-left corner
 pub type Bonus = (u8, u8);
@@ -4,4 +4,218 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-pub type Brick = (u8, u8, u8);
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use wasm4fun_graphics::draw_rect;
+use wasm4fun_time::Ticker;
+
+use crate::assets::BRICK_SPRITE;
+use crate::theme::Role;
+
+/// The number of frames a brick flashes after being hit
+const FLASH_FRAMES: u8 = 4;
+
+/// The number of frames a destroyed brick takes to shrink away
+const DYING_FRAMES: u8 = 12;
+
+/// The number of frames between two shimmers of an indestructible brick
+const SHIMMER_PERIOD: u64 = 120;
+
+/// The width of the shimmer band
+const SHIMMER_WIDTH: u32 = 2;
+
+/// A brick on the board
+///
+/// Besides its position and health, a brick holds the state of its
+/// animations.
+/// A destroyed brick stays on the board while it shrinks away, but it is
+/// ignored by collisions and does not count for completing the level.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct Brick {
+    x: u8,
+    y: u8,
+    health: u8,
+    indestructible: bool,
+    flash: u8,
+    dying: u8,
+}
+
+impl Brick {
+    /// The width of a brick
+    pub const WIDTH: u32 = 24;
+
+    /// The height of a brick
+    pub const HEIGHT: u32 = 8;
+
+    /// Create a new brick with some health
+    pub fn new(x: u8, y: u8, health: u8) -> Self {
+        Self {
+            x,
+            y,
+            health,
+            ..Default::default()
+        }
+    }
+
+    /// Create a new brick that cannot be destroyed
+    pub fn indestructible(x: u8, y: u8) -> Self {
+        Self {
+            x,
+            y,
+            indestructible: true,
+            ..Default::default()
+        }
+    }
+
+    /// Return the position of the top-left corner in board coordinates
+    pub fn position(&self) -> (u8, u8) {
+        (self.x, self.y)
+    }
+
+    /// Return the health of the brick
+    #[cfg(feature = "debug")]
+    pub fn health(&self) -> u8 {
+        self.health
+    }
+
+    /// Return whether the ball bounces on the brick
+    pub fn is_solid(&self) -> bool {
+        self.indestructible || self.health > 0
+    }
+
+    /// Return whether the brick must still be destroyed to complete the level
+    pub fn is_remaining(&self) -> bool {
+        !self.indestructible && self.health > 0
+    }
+
+    /// Return whether the brick was destroyed in this frame
+    pub fn is_just_destroyed(&self) -> bool {
+        self.health == 0 && self.dying == DYING_FRAMES
+    }
+
+    /// Return whether the brick is destroyed and its animation is over
+    pub fn is_gone(&self) -> bool {
+        !self.is_solid() && self.dying == 0
+    }
+
+    /// Hit the brick with a ball of some strength and start flashing
+    ///
+    /// Return the health left, or `None` if the brick is indestructible.
+    pub fn hit(&mut self, strength: u8) -> Option<u8> {
+        self.flash = FLASH_FRAMES;
+        if self.indestructible {
+            return None;
+        }
+
+        self.health -= strength.min(self.health);
+        if self.health == 0 {
+            self.dying = DYING_FRAMES;
+        }
+        Some(self.health)
+    }
+
+    /// Advance the animations by one frame
+    pub fn update(&mut self) {
+        self.flash = self.flash.saturating_sub(1);
+        self.dying = self.dying.saturating_sub(1);
+    }
+
+    /// Draw the brick at the given screen coordinates
+    ///
+    /// A damaged brick is covered by cracks, a dying brick shrinks towards
+    /// its center, and an indestructible brick shimmers every now and then.
+    pub fn draw(&self, x: i32, y: i32) {
+        if self.health == 0 && !self.indestructible {
+            self.draw_dying(x, y);
+            return;
+        }
+
+        let role = if self.flash > 0 {
+            Role::FlashSprite
+        } else if self.indestructible {
+            Role::MutedSprite
+        } else {
+            Role::Sprite
+        };
+        role.set();
+        BRICK_SPRITE
+            .clip(0, 0, Self::WIDTH, Self::HEIGHT)
+            .blit(x, y);
+
+        if self.indestructible {
+            self.draw_shimmer(x, y);
+            return;
+        }
+
+        Role::Sprite.set();
+        if self.health < 3 {
+            BRICK_SPRITE
+                .clip(0, Self::HEIGHT, Self::WIDTH, Self::HEIGHT)
+                .blit(x, y);
+        }
+        if self.health < 2 {
+            BRICK_SPRITE
+                .clip(0, 2 * Self::HEIGHT, Self::WIDTH, Self::HEIGHT)
+                .blit(x, y);
+        }
+    }
+
+    fn draw_dying(&self, x: i32, y: i32) {
+        let width = Self::WIDTH * self.dying as u32 / DYING_FRAMES as u32;
+        let height = Self::HEIGHT * self.dying as u32 / DYING_FRAMES as u32;
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let src_x = (Self::WIDTH - width) / 2;
+        let src_y = (Self::HEIGHT - height) / 2;
+        Role::Sprite.set();
+        BRICK_SPRITE
+            .clip(src_x, src_y, width, height)
+            .blit(x + src_x as i32, y + src_y as i32);
+    }
+
+    /// Sweep a light band across the brick
+    ///
+    /// Neighbouring bricks shimmer one after the other.
+    fn draw_shimmer(&self, x: i32, y: i32) {
+        let phase = (Ticker.since_startup() + self.x as u64 / 2 + self.y as u64) % SHIMMER_PERIOD;
+        let offset = phase as u32;
+        if offset + SHIMMER_WIDTH > Self::WIDTH {
+            return;
+        }
+
+        Role::AccentFill.set();
+        draw_rect(x + offset as i32, y + 1, SHIMMER_WIDTH, Self::HEIGHT - 3);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_destroyed_brick_is_ignored_while_dying() {
+        let mut brick = Brick::new(0, 0, 2);
+        assert_eq!(brick.hit(1), Some(1));
+        assert!(brick.is_solid() && brick.is_remaining());
+        assert!(!brick.is_just_destroyed());
+
+        assert_eq!(brick.hit(3), Some(0));
+        assert!(brick.is_just_destroyed());
+        assert!(!brick.is_solid() && !brick.is_remaining());
+        assert!(!brick.is_gone());
+
+        for _ in 0..DYING_FRAMES {
+            brick.update();
+        }
+        assert!(brick.is_gone());
+
+        let mut brick = Brick::indestructible(0, 0);
+        assert_eq!(brick.hit(3), None);
+        assert!(brick.is_solid() && !brick.is_remaining());
+        assert!(!brick.is_gone());
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use crate::random::Generator;
+use crate::ELAPSED_TIME_IN_SECONDS;
+
+/// The largest offset in pixels, reached at full intensity
+const MAX_SHAKE_OFFSET: f32 = 4.0;
+
+/// The intensity lost every second
+const SHAKE_DECAY: f32 = 2.0;
+
+/// The longest hit-stop in frames
+const MAX_HIT_STOP: u8 = 10;
+
+/// A camera offsetting the board drawing, for screen shake and hit-stop
+///
+/// Shaking has an intensity between 0 and 1 which decays over time, and the
+/// offset grows with its square so that small impacts barely move the board.
+/// Hit-stop freezes the simulation for some frames, while the camera keeps
+/// shaking.
+/// Both effects are ignored unless enabled, so that players sensitive to
+/// motion can turn them off.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct Camera {
+    intensity: f32,
+    frozen: u8,
+    offset: (i32, i32),
+    shake_enabled: bool,
+    hit_stop_enabled: bool,
+}
+
+impl Camera {
+    /// Enable or disable screen shake and hit-stop
+    pub fn set_enabled(&mut self, shake_enabled: bool, hit_stop_enabled: bool) {
+        self.shake_enabled = shake_enabled;
+        self.hit_stop_enabled = hit_stop_enabled;
+        if !shake_enabled {
+            self.intensity = 0.0;
+            self.offset = (0, 0);
+        }
+        if !hit_stop_enabled {
+            self.frozen = 0;
+        }
+    }
+
+    /// Return the offset to apply to board drawing
+    pub fn offset(&self) -> (i32, i32) {
+        self.offset
+    }
+
+    /// Translate screen coordinates by the camera offset
+    pub fn translate(&self, x: i32, y: i32) -> (i32, i32) {
+        let (dx, dy) = self.offset();
+        (x + dx, y + dy)
+    }
+
+    /// Shake the screen with an intensity between 0 and 1
+    ///
+    /// Intensities add up, up to 1.
+    pub fn shake(&mut self, intensity: f32) {
+        if self.shake_enabled {
+            self.intensity = (self.intensity + intensity).clamp(0.0, 1.0);
+        }
+    }
+
+    /// Freeze the simulation for some frames
+    pub fn hit_stop(&mut self, frames: u8) {
+        if self.hit_stop_enabled {
+            self.frozen = self.frozen.max(frames.min(MAX_HIT_STOP));
+        }
+    }
+
+    /// Return whether the simulation is frozen by a hit-stop
+    pub fn is_frozen(&self) -> bool {
+        self.frozen > 0
+    }
+
+    /// Stop shaking and unfreeze the simulation
+    pub fn reset(&mut self) {
+        self.intensity = 0.0;
+        self.frozen = 0;
+        self.offset = (0, 0);
+    }
+
+    /// Move the camera and count down the hit-stop
+    pub fn update(&mut self, generator: &mut Generator) {
+        self.frozen = self.frozen.saturating_sub(1);
+
+        if self.intensity > 0.0 {
+            let amplitude = (MAX_SHAKE_OFFSET * self.intensity * self.intensity) as i32;
+            self.offset = (
+                generator.gen_range(-amplitude..amplitude + 1),
+                generator.gen_range(-amplitude..amplitude + 1),
+            );
+            self.intensity = (self.intensity - SHAKE_DECAY * ELAPSED_TIME_IN_SECONDS).max(0.0);
+        } else {
+            self.offset = (0, 0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_camera_effects_can_be_disabled() {
+        let mut generator = Generator::new(42);
+        let mut camera = Camera::default();
+
+        camera.shake(1.0);
+        camera.hit_stop(3);
+        camera.update(&mut generator);
+        assert_eq!(camera.offset(), (0, 0));
+        assert!(!camera.is_frozen());
+
+        camera.set_enabled(true, true);
+        camera.hit_stop(3);
+        for _ in 0..3 {
+            assert!(camera.is_frozen());
+            camera.update(&mut generator);
+        }
+        assert!(!camera.is_frozen());
+
+        camera.shake(1.0);
+        for _ in 0..60 {
+            camera.update(&mut generator);
+            let (x, y) = camera.offset();
+            assert!(x.abs() <= MAX_SHAKE_OFFSET as i32 && y.abs() <= MAX_SHAKE_OFFSET as i32);
+        }
+        assert_eq!(camera.offset(), (0, 0));
+    }
+}
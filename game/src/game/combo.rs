@@ -0,0 +1,109 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+/// The number of consecutive hits needed to raise the multiplier
+const HITS_PER_MULTIPLIER: u8 = 4;
+
+/// The highest score multiplier
+const MAX_MULTIPLIER: u32 = 4;
+
+/// The highest number of semitones the hit tone is raised by
+const MAX_SEMITONES: u8 = 12;
+
+/// The shortest combo awarding a bonus when it ends
+const MIN_BONUS_HITS: u8 = 3;
+
+/// The bonus awarded for each hit of a finished combo
+const BONUS_PER_HIT: u32 = 50;
+
+/// A chain of consecutive brick hits without touching the bar
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct Combo {
+    hits: u8,
+    finished: u8,
+}
+
+impl Combo {
+    /// Return the number of consecutive hits
+    pub fn hits(&self) -> u8 {
+        self.hits
+    }
+
+    /// Count a brick hit
+    pub fn hit(&mut self) {
+        self.hits = self.hits.saturating_add(1);
+    }
+
+    /// End the combo when the ball touches the bar
+    ///
+    /// The finished combo is kept until [`Combo::take_finished`] is called.
+    pub fn end(&mut self) {
+        if self.hits > 0 {
+            self.finished = self.hits;
+        }
+        self.hits = 0;
+    }
+
+    /// Discard the combo without awarding anything, e.g. when the ball is lost
+    pub fn clear(&mut self) {
+        self.hits = 0;
+        self.finished = 0;
+    }
+
+    /// Return the number of hits of the last finished combo, if any
+    pub fn take_finished(&mut self) -> Option<u8> {
+        let finished = self.finished;
+        self.finished = 0;
+        (finished > 0).then_some(finished)
+    }
+
+    /// Return the current score multiplier
+    pub fn multiplier(&self) -> u32 {
+        let steps = self.hits.saturating_sub(1) / HITS_PER_MULTIPLIER;
+        (1 + steps as u32).min(MAX_MULTIPLIER)
+    }
+
+    /// Return the number of semitones the hit tone is raised by
+    pub fn semitones(&self) -> u8 {
+        self.hits.saturating_sub(1).min(MAX_SEMITONES)
+    }
+
+    /// Return the bonus awarded for a finished combo
+    pub fn bonus(hits: u8) -> u32 {
+        if hits < MIN_BONUS_HITS {
+            0
+        } else {
+            BONUS_PER_HIT * hits as u32 * hits as u32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_combo_ends_on_bar_bounce() {
+        let mut combo = Combo::default();
+        for _ in 0..5 {
+            combo.hit();
+        }
+        assert_eq!(combo.multiplier(), 2);
+        assert_eq!(combo.semitones(), 4);
+
+        combo.end();
+        assert_eq!(combo.hits(), 0);
+        assert_eq!(combo.multiplier(), 1);
+        assert_eq!(combo.take_finished(), Some(5));
+        assert_eq!(combo.take_finished(), None);
+        assert_eq!(Combo::bonus(5), 1250);
+        assert_eq!(Combo::bonus(2), 0);
+    }
+}
@@ -0,0 +1,69 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+/// A difficulty level
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    /// The number of difficulty levels
+    pub const COUNT: usize = 3;
+
+    /// Return the difficulty level at the given index
+    pub fn from_index(index: usize) -> Self {
+        match index % Self::COUNT {
+            0 => Difficulty::Easy,
+            1 => Difficulty::Normal,
+            _ => Difficulty::Hard,
+        }
+    }
+
+    /// Return the index of the difficulty level
+    pub fn index(&self) -> usize {
+        match self {
+            Difficulty::Easy => 0,
+            Difficulty::Normal => 1,
+            Difficulty::Hard => 2,
+        }
+    }
+
+    /// Return the name of the difficulty level
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "EASY",
+            Difficulty::Normal => "NORMAL",
+            Difficulty::Hard => "HARD",
+        }
+    }
+
+    /// Return the next difficulty level, wrapping around
+    pub fn next(&self) -> Self {
+        Self::from_index(self.index() + 1)
+    }
+
+    /// Return the previous difficulty level, wrapping around
+    pub fn previous(&self) -> Self {
+        Self::from_index(self.index() + Self::COUNT - 1)
+    }
+
+    /// Return the initial speed of the ball
+    pub fn ball_speed(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 60.0,
+            Difficulty::Normal => 80.0,
+            Difficulty::Hard => 100.0,
+        }
+    }
+}
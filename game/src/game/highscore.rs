@@ -10,11 +10,11 @@ use serde::{Deserialize, Serialize};
 use tinyvec::array_vec;
 use tinyvec::ArrayVec;
 
-use wasm4fun_log::debug;
-use wasm4fun_storage::{load, store};
+use super::{Difficulty, GameMode};
 
 const MAX_HIGH_SCORES: usize = 5;
 const MAX_HIGH_SCORES_PLUS_ONE: usize = MAX_HIGH_SCORES + 1;
+const MAX_LEADERBOARDS: usize = GameMode::COUNT * Difficulty::COUNT;
 
 /// A highscore
 #[derive(Debug)]
@@ -77,23 +77,16 @@ impl HighScores {
         self.0.truncate(MAX_HIGH_SCORES);
     }
 
-    /// Save the highscores to storage
-    pub fn save(&self) {
-        store::<&Self, 128>(self)
-    }
-
-    /// Save the highscores from storage
-    pub fn load() -> Self {
-        let highscores: Self = load::<Self, 128>();
-        debug!("Loaded {} highscores", highscores.len());
-        highscores
-    }
-
     /// Return an iterator over the highscores
     pub fn iter(&self) -> impl Iterator<Item = &HighScore> {
         self.0.iter()
     }
 
+    /// Return the best score in the list, if any
+    pub fn best(&self) -> Option<u32> {
+        self.0.iter().map(HighScore::score).max()
+    }
+
     /// Checks whether the list is empty
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
@@ -110,3 +103,107 @@ impl Default for HighScores {
         Self(array_vec!())
     }
 }
+
+/// A leaderboard, identified by game mode and difficulty
+///
+/// Each leaderboard has its own list of highscores.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct Leaderboard {
+    mode: GameMode,
+    difficulty: Difficulty,
+}
+
+impl Leaderboard {
+    /// Create a new leaderboard key
+    pub fn new(mode: GameMode, difficulty: Difficulty) -> Self {
+        Self { mode, difficulty }
+    }
+
+    /// Return the game mode of the leaderboard
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    /// Return the difficulty of the leaderboard
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// Return the next leaderboard, wrapping around
+    ///
+    /// Leaderboards are sorted by game mode first, and by difficulty second.
+    pub fn next(&self) -> Self {
+        Self::from_index((self.index() + 1) % MAX_LEADERBOARDS)
+    }
+
+    /// Return the previous leaderboard, wrapping around
+    pub fn previous(&self) -> Self {
+        Self::from_index((self.index() + MAX_LEADERBOARDS - 1) % MAX_LEADERBOARDS)
+    }
+
+    fn from_index(index: usize) -> Self {
+        Self {
+            mode: GameMode::from_index(index / Difficulty::COUNT),
+            difficulty: Difficulty::from_index(index % Difficulty::COUNT),
+        }
+    }
+
+    fn index(&self) -> usize {
+        self.mode.index() * Difficulty::COUNT + self.difficulty.index()
+    }
+}
+
+/// All lists of highscores, one for each leaderboard
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct HighScoreTables([HighScores; MAX_LEADERBOARDS]);
+
+impl HighScoreTables {
+    /// Return the list of highscores for a leaderboard
+    pub fn get(&self, leaderboard: Leaderboard) -> &HighScores {
+        &self.0[leaderboard.index()]
+    }
+
+    /// Return the mutable list of highscores for a leaderboard
+    pub fn get_mut(&mut self, leaderboard: Leaderboard) -> &mut HighScores {
+        &mut self.0[leaderboard.index()]
+    }
+
+    /// Return the total number of highscores in all leaderboards
+    pub fn len(&self) -> usize {
+        self.0.iter().map(HighScores::len).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leaderboard_cycle() {
+        let first = Leaderboard::default();
+        let mut leaderboard = first;
+        for _ in 0..MAX_LEADERBOARDS {
+            leaderboard = leaderboard.next();
+        }
+        assert_eq!(leaderboard, first);
+        assert_eq!(first.next().previous(), first);
+    }
+
+    #[test]
+    fn test_tables_are_separate() {
+        let mut tables = HighScoreTables::default();
+        let easy = Leaderboard::new(GameMode::Classic, Difficulty::Easy);
+        let hard = Leaderboard::new(GameMode::Classic, Difficulty::Hard);
+
+        tables
+            .get_mut(easy)
+            .add(HighScore::new(['A', 'B', 'C'], 100));
+
+        assert_eq!(tables.get(easy).len(), 1);
+        assert_eq!(tables.get(easy).best(), Some(100));
+        assert!(tables.get(hard).is_empty());
+        assert_eq!(tables.get(hard).best(), None);
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+/// The fraction of the remaining difference the counter rolls every frame
+const ROLL_DIVISOR: u32 = 8;
+
+/// The number of frames the high score banner is shown
+const BANNER_FRAMES: u8 = 120;
+
+/// The state of the heads-up display
+///
+/// The displayed score rolls up towards the real one, and a banner is shown
+/// once when the run passes the best score of its leaderboard.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct Hud {
+    displayed_score: u32,
+    high_score: Option<u32>,
+    high_score_beaten: bool,
+    banner: u8,
+}
+
+impl Hud {
+    /// Set the high score to beat, given the current score
+    ///
+    /// The current score is displayed without rolling, and no banner is shown
+    /// if it is already higher, e.g. when resuming a campaign run.
+    pub fn set_high_score(&mut self, high_score: Option<u32>, score: u32) {
+        self.displayed_score = score;
+        self.high_score = high_score;
+        self.high_score_beaten = high_score.is_some_and(|high_score| score > high_score);
+        self.banner = 0;
+    }
+
+    /// Return the score to display
+    pub fn displayed_score(&self) -> u32 {
+        self.displayed_score
+    }
+
+    /// Return whether the high score banner is shown
+    pub fn is_banner_shown(&self) -> bool {
+        self.banner > 0
+    }
+
+    /// Return the number of frames the banner is still shown
+    pub fn banner_frames(&self) -> u8 {
+        self.banner
+    }
+
+    /// Roll the displayed score and check the high score
+    pub fn update(&mut self, score: u32) {
+        if self.displayed_score < score {
+            let step = ((score - self.displayed_score) / ROLL_DIVISOR).max(1);
+            self.displayed_score += step;
+        } else {
+            self.displayed_score = score;
+        }
+
+        self.banner = self.banner.saturating_sub(1);
+        if !self.high_score_beaten && self.high_score.is_some_and(|high_score| score > high_score) {
+            self.high_score_beaten = true;
+            self.banner = BANNER_FRAMES;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hud_rolls_score_and_shows_banner_once() {
+        let mut hud = Hud::default();
+        hud.set_high_score(Some(500), 0);
+
+        hud.update(400);
+        assert!(hud.displayed_score() > 0 && hud.displayed_score() < 400);
+        assert!(!hud.is_banner_shown());
+
+        for _ in 0..100 {
+            hud.update(400);
+        }
+        assert_eq!(hud.displayed_score(), 400);
+
+        hud.update(600);
+        assert!(hud.is_banner_shown());
+        for _ in 0..BANNER_FRAMES {
+            hud.update(700);
+        }
+        assert!(!hud.is_banner_shown());
+
+        hud.set_high_score(Some(500), 700);
+        hud.update(800);
+        assert!(!hud.is_banner_shown());
+    }
+}
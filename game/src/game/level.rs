@@ -0,0 +1,215 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use tinyvec::array_vec;
+use tinyvec::ArrayVec;
+
+use crate::theme::Theme;
+
+use super::{Background, Brick, Playfield};
+
+pub const MAX_BRICKS: usize = 30;
+
+/// All levels, in the order they are played in campaign mode
+#[rustfmt::skip]
+pub const LEVELS: [Level; 6] = [
+    Level::new(&[
+        "333333",
+        "333333",
+        "333333",
+        "333333",
+    ]).with_background(Background::STARFIELD),
+    Level::new(&[
+        "3.33.3",
+        "323323",
+        "222222",
+        "1.11.1",
+    ]).with_background(Background::LATTICE),
+    Level::new(&[
+        "..33..",
+        ".3223.",
+        "321123",
+        ".3223.",
+        "..33..",
+    ]).with_theme(Theme::Gold).with_background(Background::CHECKER),
+    Level::new(&[
+        "333333",
+        "1#..#1",
+        "3.33.3",
+        "1#..#1",
+        "333333",
+    ]),
+    Level::new(&[
+        "3.3.3.",
+        ".3.3.3",
+        "3.3.3.",
+        ".3.3.3",
+        "333333",
+    ]).with_theme(Theme::IceCream).with_background(Background::STARFIELD),
+    Level::new(&[
+        "3333",
+        "2112",
+        "3..3",
+        "2112",
+        "#33#",
+    ]).with_playfield(Playfield::new(96, 120, 32)).with_background(Background::LATTICE),
+];
+
+/// A level layout
+///
+/// A level is defined as a list of rows, one character per brick.
+/// Digits from `1` to `3` represent a brick with that initial health,
+/// `#` represents an indestructible brick, which does not need to be
+/// destroyed to complete the level, and any other character represents an
+/// empty slot.
+/// A level can override the colour theme, choose an animated background, and
+/// change the size of the board and the thickness of the walls.
+pub struct Level {
+    rows: &'static [&'static str],
+    theme: Option<Theme>,
+    background: Background,
+    playfield: Playfield,
+}
+
+impl Level {
+    /// Create a new level from its rows
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bricks do not fit on the board above the bar, or if
+    /// there are more than `MAX_BRICKS` of them.
+    pub const fn new(rows: &'static [&'static str]) -> Self {
+        check_rows_fit(rows, Playfield::DEFAULT);
+        Self {
+            rows,
+            theme: None,
+            background: Background::PLAIN,
+            playfield: Playfield::DEFAULT,
+        }
+    }
+
+    /// Override the colour theme for this level
+    pub const fn with_theme(self, theme: Theme) -> Self {
+        Self {
+            theme: Some(theme),
+            ..self
+        }
+    }
+
+    /// Set the background for this level
+    pub const fn with_background(self, background: Background) -> Self {
+        Self { background, ..self }
+    }
+
+    /// Set the board size and wall thickness for this level
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bricks do not fit on the board above the bar.
+    pub const fn with_playfield(self, playfield: Playfield) -> Self {
+        check_rows_fit(self.rows, playfield);
+        Self { playfield, ..self }
+    }
+
+    /// Return the colour theme override, if any
+    pub fn theme(&self) -> Option<Theme> {
+        self.theme
+    }
+
+    /// Return the background
+    pub fn background(&self) -> Background {
+        self.background
+    }
+
+    /// Return the board size and wall thickness
+    pub fn playfield(&self) -> Playfield {
+        self.playfield
+    }
+
+    /// Return the level at the given index
+    ///
+    /// Indices past the last level wrap around.
+    pub fn get(index: u8) -> &'static Level {
+        &LEVELS[index as usize % LEVELS.len()]
+    }
+
+    /// Return the number of levels
+    pub fn count() -> u8 {
+        LEVELS.len() as u8
+    }
+
+    /// Return the bricks in this level
+    pub fn bricks(&self) -> ArrayVec<[Brick; MAX_BRICKS]> {
+        let mut bricks = array_vec!();
+        for (row, line) in self.rows.iter().enumerate() {
+            for (column, c) in line.bytes().enumerate() {
+                let x = (column as u32 * Brick::WIDTH) as u8;
+                let y = (row as u32 * Brick::HEIGHT) as u8;
+                match c {
+                    b'1'..=b'3' => bricks.push(Brick::new(x, y, c - b'0')),
+                    b'#' => bricks.push(Brick::indestructible(x, y)),
+                    _ => {}
+                }
+            }
+        }
+        bricks
+    }
+}
+
+/// Check that the bricks of some rows fit on a board above the bar
+///
+/// Levels are built in a constant, so this is checked at compile time.
+const fn check_rows_fit(rows: &[&str], playfield: Playfield) {
+    assert!(
+        (rows.len() as u32 * Brick::HEIGHT) as i32 <= playfield.bar_y(),
+        "level rows reach the bar"
+    );
+    let mut count = 0;
+    let mut row = 0;
+    while row < rows.len() {
+        let cells = rows[row].as_bytes();
+        assert!(
+            cells.len() as u32 * Brick::WIDTH <= playfield.width(),
+            "level row is wider than the board"
+        );
+        let mut column = 0;
+        while column < cells.len() {
+            if matches!(cells[column], b'1'..=b'3' | b'#') {
+                count += 1;
+            }
+            column += 1;
+        }
+        row += 1;
+    }
+    assert!(count <= MAX_BRICKS, "level has too many bricks");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bricks_lie_on_the_board_of_their_level() {
+        assert!(LEVELS
+            .iter()
+            .any(|level| level.playfield() != Playfield::DEFAULT));
+
+        for level in LEVELS.iter() {
+            let playfield = level.playfield();
+            for brick in level.bricks() {
+                let (x, y) = brick.position();
+                assert!(x as u32 + Brick::WIDTH <= playfield.width());
+                assert!(y as i32 + (Brick::HEIGHT as i32) <= playfield.bar_y());
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "too many bricks")]
+    fn test_levels_hold_at_most_max_bricks() {
+        Level::new(&["111111", "111111", "111111", "111111", "111111", "1"]);
+    }
+}
@@ -6,28 +6,49 @@
 
 use core::mem::MaybeUninit;
 
-use wasm4fun_graphics::Palette;
 use wasm4fun_input::GamePad;
 use wasm4fun_time::Ticker;
 
+use crate::game::SaveData;
 use crate::statemachine::Machine;
 
 use crate::audio::AudioQueue;
 
+#[cfg(feature = "debug")]
+use crate::debugoverlay::DebugOverlay;
+
+#[cfg(feature = "snapshot")]
+use crate::snapshot::QuickSave;
+
 /// The game state machine
 pub static mut STATE_MACHINE: MaybeUninit<Machine> = MaybeUninit::uninit();
 
 /// The game audio queue
 pub static mut AUDIO_QUEUE: MaybeUninit<AudioQueue> = MaybeUninit::uninit();
 
+/// The debug overlay
+#[cfg(feature = "debug")]
+pub static mut DEBUG_OVERLAY: DebugOverlay = DebugOverlay::new();
+
+/// The quick-save slot
+#[cfg(feature = "snapshot")]
+pub static mut QUICK_SAVE: QuickSave = QuickSave::new();
+
 #[no_mangle]
 fn start() {
-    Palette::Default.set();
+    SaveData::load().settings().theme().apply();
 
     // Initialize state machine
     let state_machine = unsafe { &mut STATE_MACHINE };
     state_machine.write(Machine::new());
 
+    #[cfg(feature = "snapshot")]
+    {
+        let state_machine = unsafe { (*core::ptr::addr_of_mut!(STATE_MACHINE)).assume_init_mut() };
+        let quick_save = unsafe { &mut *core::ptr::addr_of_mut!(QUICK_SAVE) };
+        quick_save.load_embedded(state_machine);
+    }
+
     // Initialize audio queue
     let audio_queue = unsafe { &mut AUDIO_QUEUE };
     audio_queue.write(AudioQueue::new());
@@ -44,8 +65,27 @@ fn update() {
     let gamepad = GamePad::open(1);
 
     state_machine.draw(gamepad);
+
+    #[cfg(feature = "debug")]
+    {
+        let debug_overlay = unsafe { &*core::ptr::addr_of!(DEBUG_OVERLAY) };
+        debug_overlay.draw(state_machine);
+    }
+
     state_machine.update(gamepad, audio_queue);
 
+    #[cfg(feature = "debug")]
+    {
+        let debug_overlay = unsafe { &mut *core::ptr::addr_of_mut!(DEBUG_OVERLAY) };
+        debug_overlay.update(gamepad, state_machine);
+    }
+
+    #[cfg(feature = "snapshot")]
+    {
+        let quick_save = unsafe { &mut *core::ptr::addr_of_mut!(QUICK_SAVE) };
+        quick_save.update(gamepad, state_machine);
+    }
+
     audio_queue.update();
 
     Ticker.update();
@@ -4,6 +4,9 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+mod background;
+pub use background::Background;
+
 mod ball;
 pub use ball::Ball;
 
@@ -16,7 +19,43 @@ pub use bonus::Bonus;
 mod brick;
 pub use brick::Brick;
 
+mod camera;
+pub use camera::Camera;
+
+mod combo;
+pub use combo::Combo;
+
+mod difficulty;
+pub use difficulty::Difficulty;
+
 mod highscore;
-pub use highscore::{HighScore, HighScores};
+pub use highscore::{HighScore, HighScoreTables, Leaderboard};
+
+mod hud;
+pub use hud::Hud;
+
+mod level;
+pub use level::{Level, MAX_BRICKS};
+
+mod mode;
+pub use mode::GameMode;
+
+mod particles;
+pub use particles::Particles;
+
+mod playfield;
+pub use playfield::Playfield;
+
+mod progress;
+pub use progress::{CampaignProgress, CampaignSnapshot};
+
+mod savedata;
+pub use savedata::SaveData;
+
+mod settings;
+pub use settings::Settings;
+
+mod stats;
+pub use stats::{draw_stats_row, Stats};
 
 mod r#loop;
@@ -0,0 +1,58 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+/// A game mode
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub enum GameMode {
+    /// A single board, played until the ball is lost or all bricks are
+    /// destroyed
+    #[default]
+    Classic,
+
+    /// A sequence of boards, with progress saved between them
+    Campaign,
+}
+
+impl GameMode {
+    /// The number of game modes
+    pub const COUNT: usize = 2;
+
+    /// Return the game mode at the given index
+    pub fn from_index(index: usize) -> Self {
+        match index % Self::COUNT {
+            0 => GameMode::Classic,
+            _ => GameMode::Campaign,
+        }
+    }
+
+    /// Return the index of the game mode
+    pub fn index(&self) -> usize {
+        match self {
+            GameMode::Classic => 0,
+            GameMode::Campaign => 1,
+        }
+    }
+
+    /// Return the name of the game mode
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "CLASSIC",
+            GameMode::Campaign => "CAMPAIGN",
+        }
+    }
+
+    /// Return the number of lives at the beginning of a run
+    pub fn initial_lives(&self) -> u8 {
+        match self {
+            GameMode::Classic => 1,
+            GameMode::Campaign => 3,
+        }
+    }
+}
@@ -0,0 +1,287 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use tinyvec::ArrayVec;
+
+use wasm4fun_fmt::format_i32;
+use wasm4fun_graphics::{draw_4x4_text, draw_line, draw_point, draw_rect, SCREEN_SIZE};
+
+use crate::random::Generator;
+use crate::theme::Role;
+use crate::ELAPSED_TIME_IN_SECONDS;
+
+use super::Camera;
+
+/// The maximal number of live particles
+///
+/// Particles spawned when the system is full are dropped, except score
+/// pop-ups, which replace the oldest particle of another kind.
+pub const MAX_PARTICLES: usize = 24;
+
+const DEBRIS_COUNT: usize = 5;
+const DEBRIS_SPEED: f32 = 40.0;
+const DEBRIS_GRAVITY: f32 = 160.0;
+const DEBRIS_LIFETIME: u8 = 40;
+
+const SPARK_COUNT: usize = 6;
+const SPARK_SPEED: f32 = 70.0;
+const SPARK_LIFETIME: u8 = 15;
+
+const SCORE_SPEED: f32 = -20.0;
+const SCORE_LIFETIME: u8 = 45;
+
+const TRAIL_LIFETIME: u8 = 8;
+
+/// The kind of a particle, which defines its look and physics
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub enum ParticleKind {
+    /// A fragment of a destroyed brick, falling down
+    #[default]
+    Debris,
+
+    /// A short line flying away from a caught bonus
+    Spark,
+
+    /// The points awarded for a brick, drifting up and fading out
+    Score(u16),
+
+    /// A fading dot left behind the ball
+    Trail,
+}
+
+impl ParticleKind {
+    /// Return the downward acceleration of the particle
+    fn gravity(&self) -> f32 {
+        match self {
+            ParticleKind::Debris => DEBRIS_GRAVITY,
+            _ => 0.0,
+        }
+    }
+}
+
+/// A particle in screen coordinates
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct Particle {
+    kind: ParticleKind,
+    position: (f32, f32),
+    velocity: (f32, f32),
+    age: u8,
+    lifetime: u8,
+}
+
+impl Particle {
+    fn new(kind: ParticleKind, position: (f32, f32), velocity: (f32, f32), lifetime: u8) -> Self {
+        Self {
+            kind,
+            position,
+            velocity,
+            age: 0,
+            lifetime,
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        let (x, y) = self.position;
+        let on_screen = (0.0..SCREEN_SIZE as f32).contains(&x) && y < SCREEN_SIZE as f32;
+        self.age < self.lifetime && on_screen
+    }
+
+    fn update(&mut self) {
+        self.velocity.1 += self.kind.gravity() * ELAPSED_TIME_IN_SECONDS;
+        self.position.0 += self.velocity.0 * ELAPSED_TIME_IN_SECONDS;
+        self.position.1 += self.velocity.1 * ELAPSED_TIME_IN_SECONDS;
+        self.age = self.age.saturating_add(1);
+    }
+
+    fn draw(&self, camera: &Camera) {
+        let (x, y) = camera.translate(self.position.0 as i32, self.position.1 as i32);
+        match self.kind {
+            ParticleKind::Debris => {
+                Role::AccentFill.set();
+                let size = if self.age < self.lifetime / 2 { 2 } else { 1 };
+                draw_rect(x, y, size, size);
+            }
+            ParticleKind::Spark => {
+                Role::Text.set();
+                let (dx, dy) = (
+                    (self.velocity.0 * 0.05) as i32,
+                    (self.velocity.1 * 0.05) as i32,
+                );
+                draw_line(x, y, x + dx, y + dy);
+            }
+            ParticleKind::Score(points) => {
+                // Fade out during the last third of the lifetime
+                if self.age < self.lifetime / 3 * 2 {
+                    Role::Text.set();
+                } else {
+                    Role::Muted.set();
+                }
+                let mut buffer = [0; 8];
+                buffer[0] = b'+';
+                let length = format_i32(&mut buffer[1..], points as i32).len();
+                let text = core::str::from_utf8(&buffer[..1 + length]).unwrap_or("");
+                draw_4x4_text(text, x - (text.len() as i32 * 4) / 2, y);
+            }
+            ParticleKind::Trail => {
+                Role::Muted.set();
+                draw_point(x, y);
+            }
+        }
+    }
+}
+
+/// A fixed-capacity particle system
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct Particles {
+    particles: ArrayVec<[Particle; MAX_PARTICLES]>,
+}
+
+impl Particles {
+    /// Return the number of live particles
+    #[allow(unused)]
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    /// Remove all particles
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    fn spawn(&mut self, particle: Particle) {
+        if self.particles.len() == self.particles.capacity() {
+            if !matches!(particle.kind, ParticleKind::Score(_)) {
+                return;
+            }
+
+            // Particles are kept in spawn order, so the first one is the oldest
+            let oldest = self
+                .particles
+                .iter()
+                .position(|particle| !matches!(particle.kind, ParticleKind::Score(_)));
+            match oldest {
+                Some(index) => {
+                    self.particles.remove(index);
+                }
+                None => return,
+            }
+        }
+        self.particles.push(particle);
+    }
+
+    /// Spawn debris of a destroyed brick centred at a position
+    pub fn spawn_debris(&mut self, generator: &mut Generator, position: (f32, f32)) {
+        for _ in 0..DEBRIS_COUNT {
+            let vx = random_unit(generator) * DEBRIS_SPEED;
+            let vy = -random_unit(generator).abs() * DEBRIS_SPEED;
+            let particle = Particle::new(ParticleKind::Debris, position, (vx, vy), DEBRIS_LIFETIME);
+            self.spawn(particle);
+        }
+    }
+
+    /// Spawn sparks flying away from a position
+    pub fn spawn_sparks(&mut self, generator: &mut Generator, position: (f32, f32)) {
+        for _ in 0..SPARK_COUNT {
+            let vx = random_unit(generator) * SPARK_SPEED;
+            let vy = random_unit(generator) * SPARK_SPEED;
+            let particle = Particle::new(ParticleKind::Spark, position, (vx, vy), SPARK_LIFETIME);
+            self.spawn(particle);
+        }
+    }
+
+    /// Spawn a score pop-up at a position
+    pub fn spawn_score(&mut self, position: (f32, f32), points: u16) {
+        let particle = Particle::new(
+            ParticleKind::Score(points),
+            position,
+            (0.0, SCORE_SPEED),
+            SCORE_LIFETIME,
+        );
+        self.spawn(particle);
+    }
+
+    /// Spawn a trail dot at a position
+    pub fn spawn_trail(&mut self, position: (f32, f32)) {
+        let particle = Particle::new(ParticleKind::Trail, position, (0.0, 0.0), TRAIL_LIFETIME);
+        self.spawn(particle);
+    }
+
+    /// Move all particles and remove the expired ones
+    pub fn update(&mut self) {
+        for particle in self.particles.iter_mut() {
+            particle.update();
+        }
+        self.particles.retain(Particle::is_alive);
+    }
+
+    /// Draw all particles
+    pub fn draw(&self, camera: &Camera) {
+        for particle in self.particles.iter() {
+            particle.draw(camera);
+        }
+    }
+}
+
+/// Return a random number between -1 and 1
+fn random_unit(generator: &mut Generator) -> f32 {
+    generator.gen_range(-100..101) as f32 / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_particles_expire_and_never_overflow() {
+        let mut generator = Generator::new(42);
+        let mut particles = Particles::default();
+
+        for _ in 0..10 {
+            particles.spawn_debris(&mut generator, (80.0, 80.0));
+        }
+        assert_eq!(particles.len(), MAX_PARTICLES);
+
+        for _ in 0..DEBRIS_LIFETIME {
+            particles.update();
+        }
+        assert_eq!(particles.len(), 0);
+    }
+
+    #[test]
+    fn test_score_replaces_oldest_particle_when_full() {
+        let mut generator = Generator::new(42);
+        let mut particles = Particles::default();
+
+        particles.spawn_trail((10.0, 10.0));
+        for _ in 0..10 {
+            particles.spawn_debris(&mut generator, (80.0, 80.0));
+        }
+        assert_eq!(particles.len(), MAX_PARTICLES);
+
+        particles.spawn_score((80.0, 80.0), 10);
+        assert_eq!(particles.len(), MAX_PARTICLES);
+        assert_eq!(
+            particles.particles[MAX_PARTICLES - 1].kind,
+            ParticleKind::Score(10)
+        );
+        assert!(particles
+            .particles
+            .iter()
+            .all(|particle| particle.kind != ParticleKind::Trail));
+
+        particles.spawn_trail((10.0, 10.0));
+        assert_eq!(
+            particles.particles[MAX_PARTICLES - 1].kind,
+            ParticleKind::Score(10)
+        );
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use wasm4fun_graphics::SCREEN_SIZE;
+
+use super::Bar;
+
+/// The distance between the top of the bar and the bottom of the board
+const BAR_DISTANCE: i32 = 14;
+
+/// The height of the dashboard at the top of the screen
+const DASHBOARD_HEIGHT: u32 = 8;
+
+/// The geometry of the board and of the walls around it
+///
+/// The ball, the bar, the bricks and the bonuses live in board coordinates,
+/// with the origin at the top-left corner of the board, and are converted to
+/// screen coordinates only when drawn.
+/// The board is centered horizontally and lies on the bottom of the screen,
+/// with walls on its left, top and right sides.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct Playfield {
+    width: u32,
+    height: u32,
+    wall: u32,
+}
+
+impl Playfield {
+    /// The size of the tiles walls are made of
+    pub const WALL_TILE_SIZE: u32 = 8;
+
+    /// The playfield of the original game, filling the screen below the
+    /// dashboard
+    pub const DEFAULT: Playfield = Playfield::new(144, 144, 8);
+
+    /// Create a new playfield
+    ///
+    /// # Panics
+    ///
+    /// Panics if the wall thickness is not a multiple of the wall tile size,
+    /// if the board and its walls do not fit on the screen below the
+    /// dashboard, if the widest bar does not fit on the board, or if board
+    /// coordinates do not fit in a `u8`.
+    /// Playfields are built in constants, so this is checked at compile time.
+    pub const fn new(width: u32, height: u32, wall: u32) -> Self {
+        assert!(
+            wall.is_multiple_of(Self::WALL_TILE_SIZE),
+            "walls are not a multiple of the tile size"
+        );
+        assert!(
+            width + 2 * wall <= SCREEN_SIZE,
+            "board and walls are wider than the screen"
+        );
+        assert!(
+            height + wall + DASHBOARD_HEIGHT <= SCREEN_SIZE,
+            "board and walls are taller than the screen below the dashboard"
+        );
+        assert!(
+            width >= Bar::MAX_WIDTH && height as i32 > BAR_DISTANCE,
+            "bar does not fit on the board"
+        );
+        assert!(
+            width <= u8::MAX as u32 && height <= u8::MAX as u32,
+            "board coordinates do not fit in u8"
+        );
+        Self {
+            width,
+            height,
+            wall,
+        }
+    }
+
+    /// Return the width of the board
+    pub const fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Return the height of the board
+    pub const fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Return the thickness of the walls
+    pub const fn wall(&self) -> u32 {
+        self.wall
+    }
+
+    /// Return the screen coordinates of the top-left corner of the board
+    pub fn origin(&self) -> (i32, i32) {
+        (
+            (SCREEN_SIZE - self.width) as i32 / 2,
+            (SCREEN_SIZE - self.height) as i32,
+        )
+    }
+
+    /// Convert board coordinates to screen coordinates
+    pub fn to_screen(self, x: i32, y: i32) -> (i32, i32) {
+        let (left, top) = self.origin();
+        (x + left, y + top)
+    }
+
+    /// Convert screen coordinates to board coordinates
+    #[allow(unused)]
+    pub fn to_board(self, x: i32, y: i32) -> (i32, i32) {
+        let (left, top) = self.origin();
+        (x - left, y - top)
+    }
+
+    /// Return the vertical board coordinate of the top of the bar
+    pub const fn bar_y(&self) -> i32 {
+        self.height as i32 - BAR_DISTANCE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_board_and_screen_coordinates_roundtrip() {
+        let playfield = Playfield::DEFAULT;
+        assert_eq!(playfield.origin(), (8, 16));
+        assert_eq!(playfield.to_screen(0, 0), (8, 16));
+        assert_eq!(playfield.to_board(8, 16), (0, 0));
+        assert_eq!(playfield.bar_y(), 130);
+
+        let playfield = Playfield::new(96, 120, 16);
+        let (x, y) = playfield.to_screen(10, 20);
+        assert_eq!((x, y), (42, 60));
+        assert_eq!(playfield.to_board(x, y), (10, 20));
+    }
+
+    #[test]
+    #[should_panic(expected = "wider than the screen")]
+    fn test_board_must_fit_on_screen() {
+        Playfield::new(144, 144, 16);
+    }
+
+    #[test]
+    #[should_panic(expected = "multiple of the tile size")]
+    fn test_walls_must_be_made_of_whole_tiles() {
+        Playfield::new(144, 136, 12);
+    }
+}
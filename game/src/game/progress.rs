@@ -0,0 +1,60 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use super::{Ball, Bar, Difficulty};
+
+/// A snapshot of a campaign run, taken at the beginning of a level
+///
+/// The lives are updated whenever the ball is lost, so that quitting and
+/// continuing the run does not give back the lives lost in the level.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct CampaignSnapshot {
+    pub difficulty: Difficulty,
+    pub level: u8,
+    pub score: u32,
+    pub lives: u8,
+    pub bar: Bar,
+    pub ball: Ball,
+}
+
+/// The progress in campaign mode
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct CampaignProgress {
+    highest_level: u8,
+    snapshot: Option<CampaignSnapshot>,
+}
+
+impl CampaignProgress {
+    /// Return the highest level reached in any campaign run
+    pub fn highest_level(&self) -> u8 {
+        self.highest_level
+    }
+
+    /// Mark a level as reached
+    pub fn unlock(&mut self, level: u8) {
+        self.highest_level = self.highest_level.max(level);
+    }
+
+    /// Return the snapshot of the current run, if any
+    pub fn snapshot(&self) -> Option<&CampaignSnapshot> {
+        self.snapshot.as_ref()
+    }
+
+    /// Return the snapshot of the current run for updating, if any
+    pub fn snapshot_mut(&mut self) -> Option<&mut CampaignSnapshot> {
+        self.snapshot.as_mut()
+    }
+
+    /// Replace the snapshot of the current run
+    pub fn set_snapshot(&mut self, snapshot: Option<CampaignSnapshot>) {
+        self.snapshot = snapshot;
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "storage")]
+use wasm4fun_core::diskr;
+use wasm4fun_log::debug;
+#[cfg(not(feature = "storage"))]
+use wasm4fun_storage::load;
+use wasm4fun_storage::store;
+
+#[cfg(feature = "storage")]
+use super::highscore::HighScores;
+use super::{CampaignProgress, HighScoreTables, Settings};
+#[cfg(feature = "storage")]
+use super::{Difficulty, GameMode, Leaderboard};
+
+/// WASM-4 only supports storage for up to 1024 bytes
+const STORAGE_SIZE: usize = 1024;
+
+/// The version of the storage format, stored in the first byte
+///
+/// Older versions of the game stored a bare list of highscores, whose first
+/// byte is their number, at most 5, so versions start from 16.
+const FORMAT_VERSION: u8 = 16;
+
+/// All data persisted to storage
+///
+/// WASM-4 offers a single storage area, so everything that must survive a
+/// restart is kept together and always saved at once.
+#[derive(Debug)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct SaveData {
+    #[allow(unused)]
+    version: u8,
+    highscores: HighScoreTables,
+    progress: CampaignProgress,
+    last_name: Option<[u8; 3]>,
+    settings: Settings,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            highscores: HighScoreTables::default(),
+            progress: CampaignProgress::default(),
+            last_name: None,
+            settings: Settings::default(),
+        }
+    }
+}
+
+impl SaveData {
+    /// Save the data to storage
+    pub fn save(&self) {
+        store::<&Self, STORAGE_SIZE>(self)
+    }
+
+    /// Load the data from storage
+    ///
+    /// Highscores saved by older versions of the game are moved to the
+    /// classic normal leaderboard.
+    #[cfg(feature = "storage")]
+    pub fn load() -> Self {
+        let mut buffer = [0; STORAGE_SIZE];
+        let bytes_read = unsafe { diskr(buffer.as_mut_ptr(), buffer.len() as u32) };
+        let data = Self::from_bytes(&buffer[..bytes_read as usize]);
+        debug!("Loaded {} highscores", data.highscores.len());
+        data
+    }
+
+    /// Load the data from storage
+    #[cfg(not(feature = "storage"))]
+    pub fn load() -> Self {
+        let data: Self = load::<Self, STORAGE_SIZE>();
+        debug!("Loaded {} highscores", data.highscores.len());
+        data
+    }
+
+    /// Deserialize the data from the bytes in storage
+    #[cfg(feature = "storage")]
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let result = match bytes.first() {
+            None => return Self::default(),
+            Some(&FORMAT_VERSION) => postcard::from_bytes(bytes),
+            Some(_) => {
+                debug!("Migrating highscores from the legacy format");
+                postcard::from_bytes::<HighScores>(bytes).map(|highscores| {
+                    let mut data = Self::default();
+                    let leaderboard = Leaderboard::new(GameMode::Classic, Difficulty::Normal);
+                    *data.highscores.get_mut(leaderboard) = highscores;
+                    data
+                })
+            }
+        };
+        result.unwrap_or_else(|message| {
+            debug!("Could not deserialize save data");
+            debug!("Reason: {}", message);
+            Self::default()
+        })
+    }
+
+    /// Return the highscores
+    pub fn highscores(&self) -> &HighScoreTables {
+        &self.highscores
+    }
+
+    /// Return the mutable highscores
+    pub fn highscores_mut(&mut self) -> &mut HighScoreTables {
+        &mut self.highscores
+    }
+
+    /// Return the campaign progress
+    pub fn progress(&self) -> &CampaignProgress {
+        &self.progress
+    }
+
+    /// Return the mutable campaign progress
+    pub fn progress_mut(&mut self) -> &mut CampaignProgress {
+        &mut self.progress
+    }
+
+    /// Return the player settings
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Return the mutable player settings
+    pub fn settings_mut(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
+
+    /// Return the name last entered for a highscore, if any
+    pub fn last_name(&self) -> Option<[char; 3]> {
+        self.last_name
+            .map(|name| [name[0] as char, name[1] as char, name[2] as char])
+    }
+
+    /// Remember the name last entered for a highscore
+    pub fn set_last_name(&mut self, name: [char; 3]) {
+        self.last_name = Some([name[0] as u8, name[1] as u8, name[2] as u8]);
+    }
+}
+
+#[cfg(all(test, feature = "storage"))]
+mod tests {
+    use super::*;
+
+    use crate::game::HighScore;
+
+    #[test]
+    fn test_legacy_highscores_are_migrated() {
+        let classic = Leaderboard::new(GameMode::Classic, Difficulty::Normal);
+        let mut buffer = [0; STORAGE_SIZE];
+
+        let mut highscores = HighScores::default();
+        highscores.add(HighScore::new(['A', 'B', 'C'], 1200));
+        highscores.add(HighScore::new(['X', 'Y', 'Z'], 300));
+        let bytes = postcard::to_slice(&highscores, &mut buffer).unwrap();
+        let data = SaveData::from_bytes(bytes);
+        assert_eq!(data.version, FORMAT_VERSION);
+        assert_eq!(data.highscores().len(), 2);
+        assert_eq!(data.highscores().get(classic).best(), Some(1200));
+
+        let bytes = postcard::to_slice(&data, &mut buffer).unwrap();
+        assert_eq!(bytes[0], FORMAT_VERSION);
+        let data = SaveData::from_bytes(bytes);
+        assert_eq!(data.highscores().get(classic).best(), Some(1200));
+
+        assert_eq!(SaveData::from_bytes(&[]).highscores().len(), 0);
+    }
+}
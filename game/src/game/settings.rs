@@ -0,0 +1,73 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use crate::theme::Theme;
+
+/// The player settings
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct Settings {
+    theme: Theme,
+    ball_trail: bool,
+    screen_shake: bool,
+    hit_stop: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            theme: Theme::default(),
+            ball_trail: false,
+            screen_shake: true,
+            hit_stop: true,
+        }
+    }
+}
+
+impl Settings {
+    /// Return the colour theme
+    pub fn theme(&self) -> Theme {
+        self.theme
+    }
+
+    /// Set the colour theme
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// Return whether the ball leaves a trail
+    pub fn ball_trail(&self) -> bool {
+        self.ball_trail
+    }
+
+    /// Set whether the ball leaves a trail
+    pub fn set_ball_trail(&mut self, ball_trail: bool) {
+        self.ball_trail = ball_trail;
+    }
+
+    /// Return whether impacts shake the screen
+    pub fn screen_shake(&self) -> bool {
+        self.screen_shake
+    }
+
+    /// Set whether impacts shake the screen
+    pub fn set_screen_shake(&mut self, screen_shake: bool) {
+        self.screen_shake = screen_shake;
+    }
+
+    /// Return whether strong impacts briefly freeze the game
+    pub fn hit_stop(&self) -> bool {
+        self.hit_stop
+    }
+
+    /// Set whether strong impacts briefly freeze the game
+    pub fn set_hit_stop(&mut self, hit_stop: bool) {
+        self.hit_stop = hit_stop;
+    }
+}
@@ -0,0 +1,132 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use wasm4fun_fmt::format_i32;
+use wasm4fun_graphics::SCREEN_SIZE;
+
+use crate::font::{draw_5x8_text, draw_aligned_5x8_text, Alignment, LINE_HEIGHT};
+use crate::theme::Role;
+
+/// The time under which completing a level awards a bonus, in seconds
+const PAR_TIME: u32 = 120;
+
+/// The bonus awarded for each second under the par time
+const TIME_BONUS_PER_SECOND: u32 = 10;
+
+/// The horizontal margin of the statistics rows
+const MARGIN: i32 = 24;
+
+/// Statistics of a level or of a whole run
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct Stats {
+    time: u32,
+    bricks: u16,
+    bonuses: u16,
+    max_combo: u8,
+}
+
+impl Stats {
+    /// Return the playing time in seconds
+    pub fn time(&self) -> u32 {
+        self.time
+    }
+
+    /// Count a second of playing time
+    pub fn tick(&mut self) {
+        self.time += 1;
+    }
+
+    /// Count a destroyed brick
+    pub fn count_brick(&mut self) {
+        self.bricks = self.bricks.saturating_add(1);
+    }
+
+    /// Count a caught bonus
+    pub fn count_bonus(&mut self) {
+        self.bonuses = self.bonuses.saturating_add(1);
+    }
+
+    /// Record the length of the current combo
+    pub fn record_combo(&mut self, hits: u8) {
+        self.max_combo = self.max_combo.max(hits);
+    }
+
+    /// Add the statistics of a level to the statistics of a run
+    pub fn add(&mut self, other: &Stats) {
+        self.time += other.time;
+        self.bricks = self.bricks.saturating_add(other.bricks);
+        self.bonuses = self.bonuses.saturating_add(other.bonuses);
+        self.max_combo = self.max_combo.max(other.max_combo);
+    }
+
+    /// Return the bonus for completing a level quickly
+    pub fn time_bonus(&self) -> u32 {
+        PAR_TIME.saturating_sub(self.time) * TIME_BONUS_PER_SECOND
+    }
+
+    /// Draw the statistics as rows of labels and values
+    ///
+    /// Return the vertical coordinate after the last row.
+    pub fn draw(&self, y: i32) -> i32 {
+        let rows = [
+            ("TIME", self.time),
+            ("BRICKS", self.bricks as u32),
+            ("BONUSES", self.bonuses as u32),
+            ("MAX COMBO", self.max_combo as u32),
+        ];
+
+        let mut y = y;
+        for (label, value) in rows {
+            draw_stats_row(label, value, y);
+            y += LINE_HEIGHT;
+        }
+        y
+    }
+}
+
+/// Draw a row with a label on the left and a value on the right
+pub fn draw_stats_row(label: &str, value: u32, y: i32) {
+    let mut buffer = [0; 10];
+
+    Role::Muted.set();
+    draw_5x8_text(label, MARGIN, y);
+
+    Role::Text.set();
+    let text = format_i32(&mut buffer, value as i32);
+    let width = SCREEN_SIZE - 2 * MARGIN as u32;
+    draw_aligned_5x8_text(text, MARGIN, y, width, Alignment::Right);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_stats_add_up_to_run_stats() {
+        let mut level = Stats::default();
+        for _ in 0..100 {
+            level.tick();
+        }
+        level.count_brick();
+        level.count_bonus();
+        level.record_combo(5);
+        level.record_combo(2);
+        assert_eq!(level.time_bonus(), 200);
+
+        let mut run = Stats::default();
+        run.add(&level);
+        run.add(&level);
+        assert_eq!(run.time(), 200);
+        assert_eq!(run.bricks, 2);
+        assert_eq!(run.bonuses, 2);
+        assert_eq!(run.max_combo, 5);
+        assert_eq!(run.time_bonus(), 0);
+    }
+}
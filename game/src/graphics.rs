@@ -4,42 +4,78 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use wasm4fun_graphics::SCREEN_SIZE;
+use wasm4fun_fmt::format_i32;
+use wasm4fun_graphics::{draw_4x4_text, draw_horizontal_line, Palette, SCREEN_SIZE};
 
-use crate::assets::FONT4X8_SPRITE;
+use crate::theme::Role;
 
-pub fn draw_5x8_text(s: impl AsRef<str>, x: i32, y: i32) {
-    let s = s.as_ref();
+/// The colours of the current theme, from lightest to darkest
+///
+/// WASM-4 palette is overwritten by fading effects, so the theme colours are
+/// kept aside to restore it.
+static mut THEME_PALETTE: [u32; 4] = [0xdef7cd, 0x86bf6b, 0x306950, 0x071821];
 
-    const FONT_WIDTH: u32 = 5;
-    const FONT_HEIGHT: u32 = 8;
+fn theme_palette() -> [u32; 4] {
+    unsafe { *core::ptr::addr_of!(THEME_PALETTE) }
+}
+
+/// Set the colours of the current theme and apply them
+pub fn set_palette(colours: [u32; 4]) {
+    unsafe { *core::ptr::addr_of_mut!(THEME_PALETTE) = colours };
+    restore_palette();
+}
+
+/// Set the palette of the current theme
+pub fn restore_palette() {
+    let [one, two, three, four] = theme_palette();
+    Palette::Custom(one, two, three, four).set();
+}
 
-    for (i, c) in s.chars().enumerate() {
-        if c.is_ascii() {
-            let u = c as u32;
-            let index = u - 32;
-            let row = index / 32;
-            let column = index % 32;
+/// Set the palette of the current theme, with all colours faded towards the
+/// darkest one
+///
+/// An amount of 0 leaves the palette unchanged, and an amount of 1 turns all
+/// colours into the darkest one.
+pub fn set_faded_palette(amount: f32) {
+    let palette = theme_palette();
+    let target = palette[3];
+    let [one, two, three, four] = palette.map(|colour| blend(colour, target, amount));
+    Palette::Custom(one, two, three, four).set();
+}
 
-            let x = x + (FONT_WIDTH as i32) * (i as i32);
-            let src_x = (FONT_WIDTH as u32) * column;
-            let src_y = (FONT_HEIGHT as u32) * row;
+/// Blend two RGB colours
+fn blend(from: u32, to: u32, amount: f32) -> u32 {
+    let amount = amount.clamp(0.0, 1.0);
+    let mut blended = 0;
+    for shift in [0, 8, 16] {
+        let from = ((from >> shift) & 0xff) as f32;
+        let to = ((to >> shift) & 0xff) as f32;
+        let channel = from + (to - from) * amount;
+        blended |= (channel as u32 & 0xff) << shift;
+    }
+    blended
+}
 
-            FONT4X8_SPRITE
-                .clip(src_x, src_y, FONT_WIDTH, FONT_HEIGHT)
-                .blit(x, y);
-        }
+/// Dim the whole screen by covering every other row with the darkest colour
+pub fn dim_screen() {
+    Role::Shade.set();
+    for y in (0..SCREEN_SIZE as i32).step_by(2) {
+        draw_horizontal_line(0, y, SCREEN_SIZE);
     }
 }
 
-/// Draw centered text with 5×8 font using the current colours
-pub fn draw_centered_5x8_text<T>(s: T, y: i32)
-where
-    T: AsRef<str>,
-{
-    const FONT_WIDTH: u32 = 5;
+/// Draw a label followed by a list of numbers with 4×4 font using the current
+/// colours
+pub fn draw_numbers(label: &str, values: &[i32], x: i32, y: i32) {
+    const FONT_WIDTH: i32 = 4;
 
-    let s = s.as_ref();
-    let x = ((SCREEN_SIZE - s.len() as u32 * FONT_WIDTH) / 2) as i32;
-    draw_5x8_text(s, x, y)
+    draw_4x4_text(label, x, y);
+
+    let mut x = x + (label.len() as i32 + 1) * FONT_WIDTH;
+    let mut buffer = [0; 12];
+    for value in values {
+        let text = format_i32(&mut buffer, *value);
+        draw_4x4_text(text, x, y);
+        x += (text.len() as i32 + 1) * FONT_WIDTH;
+    }
 }
@@ -0,0 +1,205 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! On-screen grid keyboard
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use wasm4fun_graphics::draw_rect;
+
+use crate::font::{draw_aligned_5x8_text, Alignment};
+use crate::theme::Role;
+
+const COLUMNS: usize = 10;
+const ROWS: usize = 5;
+const CELL_WIDTH: u32 = 14;
+const CELL_HEIGHT: u32 = 12;
+
+/// Characters on the keyboard, one row per line
+///
+/// The last row is reserved for the special keys.
+const CHARACTERS: [&[u8; COLUMNS]; ROWS - 1] =
+    [b"ABCDEFGHIJ", b"KLMNOPQRST", b"UVWXYZ.-!?", b"0123456789"];
+
+/// A key on the keyboard
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Key {
+    /// A printable character
+    Character(char),
+
+    /// The space character
+    Space,
+
+    /// Delete the last character
+    Delete,
+
+    /// Confirm the entered text
+    End,
+}
+
+impl Key {
+    /// Return the first and last column of a key in the special keys row
+    fn columns(&self) -> (usize, usize) {
+        match self {
+            Key::Space => (0, 2),
+            Key::Delete => (3, 6),
+            Key::End => (7, 9),
+            Key::Character(_) => (0, 0),
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Key::Space => "SPC",
+            Key::Delete => "DEL",
+            Key::End => "END",
+            Key::Character(_) => "",
+        }
+    }
+}
+
+/// An on-screen grid keyboard
+///
+/// The keyboard is navigated with the directional buttons, and navigation
+/// wraps around at the edges.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct Keyboard {
+    row: usize,
+    column: usize,
+}
+
+impl Keyboard {
+    /// Return the key under the cursor
+    pub fn selected(&self) -> Key {
+        Self::key_at(self.row, self.column)
+    }
+
+    /// Move the cursor on a key
+    pub fn select(&mut self, key: Key) {
+        for row in 0..ROWS {
+            for column in 0..COLUMNS {
+                if Self::key_at(row, column) == key {
+                    self.row = row;
+                    self.column = column;
+                    return;
+                }
+            }
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        self.row = (self.row + ROWS - 1) % ROWS;
+    }
+
+    pub fn move_down(&mut self) {
+        self.row = (self.row + 1) % ROWS;
+    }
+
+    pub fn move_left(&mut self) {
+        if self.row == ROWS - 1 {
+            // Skip to the beginning of the previous special key
+            let (first, _last) = self.selected().columns();
+            self.column = (first + COLUMNS - 1) % COLUMNS;
+            self.column = self.selected().columns().0;
+        } else {
+            self.column = (self.column + COLUMNS - 1) % COLUMNS;
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if self.row == ROWS - 1 {
+            // Skip to the beginning of the next special key
+            let (_first, last) = self.selected().columns();
+            self.column = (last + 1) % COLUMNS;
+        } else {
+            self.column = (self.column + 1) % COLUMNS;
+        }
+    }
+
+    /// Draw the keyboard with its top-left corner at the given coordinates
+    pub fn draw(&self, x: i32, y: i32) {
+        let mut tmp = [0u8; 1];
+
+        for (row, characters) in CHARACTERS.iter().enumerate() {
+            for (column, character) in characters.iter().enumerate() {
+                let cell_x = x + (column as u32 * CELL_WIDTH) as i32;
+                let cell_y = y + (row as u32 * CELL_HEIGHT) as i32;
+                self.draw_cell_background(row, column, cell_x, cell_y, CELL_WIDTH);
+                let text = (*character as char).encode_utf8(&mut tmp);
+                draw_aligned_5x8_text(text, cell_x, cell_y + 2, CELL_WIDTH, Alignment::Center);
+            }
+        }
+
+        let row = ROWS - 1;
+        for key in [Key::Space, Key::Delete, Key::End] {
+            let (first, last) = key.columns();
+            let cell_x = x + (first as u32 * CELL_WIDTH) as i32;
+            let cell_y = y + (row as u32 * CELL_HEIGHT) as i32;
+            let width = (last - first + 1) as u32 * CELL_WIDTH;
+            self.draw_cell_background(row, first, cell_x, cell_y, width);
+            draw_aligned_5x8_text(key.label(), cell_x, cell_y + 2, width, Alignment::Center);
+        }
+    }
+
+    fn draw_cell_background(&self, row: usize, column: usize, x: i32, y: i32, width: u32) {
+        if Self::key_at(row, column) == self.selected() {
+            Role::AccentFill.set();
+            draw_rect(x, y, width, CELL_HEIGHT);
+            Role::Ink.set();
+        } else {
+            Role::Text.set();
+        }
+    }
+
+    fn key_at(row: usize, column: usize) -> Key {
+        if row < ROWS - 1 {
+            Key::Character(CHARACTERS[row][column] as char)
+        } else {
+            [Key::Space, Key::Delete, Key::End]
+                .into_iter()
+                .find(|key| (key.columns().0..=key.columns().1).contains(&column))
+                .unwrap_or(Key::End)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_navigation_wraps_around() {
+        let mut keyboard = Keyboard::default();
+        assert_eq!(keyboard.selected(), Key::Character('A'));
+
+        keyboard.move_left();
+        assert_eq!(keyboard.selected(), Key::Character('J'));
+
+        keyboard.move_up();
+        assert_eq!(keyboard.selected(), Key::End);
+
+        keyboard.move_right();
+        assert_eq!(keyboard.selected(), Key::Space);
+
+        keyboard.move_left();
+        assert_eq!(keyboard.selected(), Key::End);
+
+        keyboard.move_left();
+        assert_eq!(keyboard.selected(), Key::Delete);
+    }
+
+    #[test]
+    fn test_select() {
+        let mut keyboard = Keyboard::default();
+        keyboard.select(Key::End);
+        assert_eq!(keyboard.selected(), Key::End);
+
+        keyboard.move_down();
+        assert_eq!(keyboard.selected(), Key::Character('H'));
+    }
+}
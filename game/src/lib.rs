@@ -14,20 +14,105 @@ use wasm4fun_panichandler::*;
 #[cfg(feature = "buddy-alloc")]
 mod alloc;
 
+mod animation;
+
 mod audio;
 
 mod assets;
 
+#[cfg(feature = "debug")]
+mod debugoverlay;
+
+mod font;
+
 mod game;
 
 mod graphics;
 
+mod keyboard;
+
 mod math;
 
+mod music;
+
+mod random;
+
+#[cfg(feature = "snapshot")]
+mod snapshot;
+
 mod statemachine;
 
+mod theme;
+
+/// The number of frames per second
+///
+/// The WASM-4 console uses a constant framerate of 60 Hz.
+pub const FRAMES_PER_SECOND: u32 = 60;
+
 /// The time elapsed since the previous frame
 ///
 /// Since the WASM-4 console uses a constant framerate of 60 Hz, this time is
 /// also constant.
 pub const ELAPSED_TIME_IN_SECONDS: f32 = 0.016;
+
+/// Stubs for WASM-4 storage, logging, drawing and sound functions, which are
+/// not available when running tests on the host
+#[cfg(test)]
+mod stubs {
+    #[no_mangle]
+    extern "C" fn blit(
+        _sprite: *const u8,
+        _x: i32,
+        _y: i32,
+        _width: u32,
+        _height: u32,
+        _flags: u32,
+    ) {
+    }
+
+    #[no_mangle]
+    #[allow(clippy::too_many_arguments)]
+    extern "C" fn blitSub(
+        _sprite: *const u8,
+        _x: i32,
+        _y: i32,
+        _width: u32,
+        _height: u32,
+        _src_x: u32,
+        _src_y: u32,
+        _stride: u32,
+        _flags: u32,
+    ) {
+    }
+
+    #[no_mangle]
+    extern "C" fn line(_x1: i32, _y1: i32, _x2: i32, _y2: i32) {}
+
+    #[no_mangle]
+    extern "C" fn hline(_x: i32, _y: i32, _len: u32) {}
+
+    #[no_mangle]
+    extern "C" fn vline(_x: i32, _y: i32, _len: u32) {}
+
+    #[no_mangle]
+    extern "C" fn rect(_x: i32, _y: i32, _width: u32, _height: u32) {}
+
+    #[no_mangle]
+    extern "C" fn tone(_frequency: u32, _duration: u32, _volume: u32, _flags: u32) {}
+
+    #[no_mangle]
+    extern "C" fn textUtf8(_text: *const u8, _length: usize, _x: i32, _y: i32) {}
+
+    #[no_mangle]
+    extern "C" fn traceUtf8(_trace: *const u8, _length: usize) {}
+
+    #[no_mangle]
+    extern "C" fn diskr(_dest: *mut u8, _size: u32) -> u32 {
+        0
+    }
+
+    #[no_mangle]
+    extern "C" fn diskw(_src: *const u8, size: u32) -> u32 {
+        size
+    }
+}
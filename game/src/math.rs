@@ -7,6 +7,23 @@
 #[cfg(target_family = "wasm")]
 use micromath::F32Ext;
 
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+/// A point where a ball touched a surface, with the surface normal there
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct Contact {
+    pub point: (f32, f32),
+    pub normal: (f32, f32),
+}
+
+impl Contact {
+    pub fn new(point: (f32, f32), normal: (f32, f32)) -> Self {
+        Self { point, normal }
+    }
+}
+
 pub fn intersects_vertical_line((xc, _yc): (i32, i32), r: u32, x_line: i32) -> bool {
     let radius = r as i32;
     let distance = (xc - x_line).abs();
@@ -0,0 +1,293 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Pattern-based background music
+//!
+//! A track is played on the two pulse channels and on the triangle channel.
+//! Each channel plays a sequence of patterns, and each pattern is a fixed
+//! number of steps.
+//! Every step lasts the same number of frames, which sets the tempo.
+//! When the last pattern is over, the track restarts from its loop point, if
+//! it has one.
+
+use wasm4fun_sound::{DutyCycle, Tone};
+
+use crate::audio::Channel;
+
+/// The number of steps in a pattern
+pub const PATTERN_LENGTH: usize = 16;
+
+/// The number of channels used by the music
+const CHANNELS: usize = 3;
+
+/// The channels used by the music, in order
+const MUSIC_CHANNELS: [Channel; CHANNELS] = [Channel::Pulse1, Channel::Pulse2, Channel::Triangle];
+
+/// A note, as a MIDI note number
+///
+/// Note [`REST`] is silent.
+pub type Note = u8;
+
+/// A silent note
+pub const REST: Note = 0;
+
+/// A sequence of notes, one for each step
+pub type Pattern = [Note; PATTERN_LENGTH];
+
+/// A music track
+pub struct Track {
+    /// The number of frames for each step
+    pub frames_per_step: u8,
+
+    /// The pattern to restart from after the last one, if the track loops
+    pub loop_start: Option<usize>,
+
+    /// The volume of each channel
+    pub volumes: [u8; CHANNELS],
+
+    /// The sequence of patterns for each channel
+    ///
+    /// A channel with no patterns is silent, a channel with fewer patterns
+    /// than the others repeats them.
+    pub sequences: [&'static [&'static Pattern]; CHANNELS],
+}
+
+impl Track {
+    /// Return the number of patterns in the track
+    fn length(&self) -> usize {
+        self.sequences
+            .iter()
+            .map(|sequence| sequence.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Return the note played on a channel at a given step
+    fn note(&self, channel: usize, pattern: usize, step: usize) -> Note {
+        let sequence = self.sequences[channel];
+        if sequence.is_empty() {
+            REST
+        } else {
+            sequence[pattern % sequence.len()][step]
+        }
+    }
+}
+
+/// A background music
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Music {
+    Menu,
+    Gameplay,
+    GameOver,
+}
+
+impl Music {
+    /// Return the track of the music
+    fn track(self) -> &'static Track {
+        match self {
+            Music::Menu => &MENU_TRACK,
+            Music::Gameplay => &GAMEPLAY_TRACK,
+            Music::GameOver => &GAME_OVER_TRACK,
+        }
+    }
+}
+
+/// A music player
+///
+/// Sound effects can pre-empt a channel for a number of frames, during which
+/// the music does not play any note on it.
+pub struct MusicPlayer {
+    music: Option<Music>,
+    pattern: usize,
+    step: usize,
+    frame: u8,
+    preempted: [u8; CHANNELS],
+}
+
+impl MusicPlayer {
+    /// Create a silent music player
+    pub fn new() -> Self {
+        Self {
+            music: None,
+            pattern: 0,
+            step: 0,
+            frame: 0,
+            preempted: [0; CHANNELS],
+        }
+    }
+
+    /// Play a music from the beginning, or stop playing
+    ///
+    /// Nothing changes if the music is already playing.
+    pub fn play(&mut self, music: Option<Music>) {
+        if music != self.music {
+            self.music = music;
+            self.pattern = 0;
+            self.step = 0;
+            self.frame = 0;
+        }
+    }
+
+    /// Leave a channel to a sound effect for some frames
+    pub fn preempt(&mut self, channel: Channel, frames: u8) {
+        if let Some(index) = MUSIC_CHANNELS.iter().position(|c| *c == channel) {
+            self.preempted[index] = self.preempted[index].max(frames);
+        }
+    }
+
+    /// Play the notes for the current frame
+    pub fn update(&mut self) {
+        for preempted in self.preempted.iter_mut() {
+            *preempted = preempted.saturating_sub(1);
+        }
+
+        let track = match self.music {
+            Some(music) => music.track(),
+            None => return,
+        };
+
+        if self.pattern >= track.length() {
+            return;
+        }
+
+        if self.frame == 0 {
+            self.play_step(track);
+        }
+
+        self.frame += 1;
+        if self.frame >= track.frames_per_step {
+            self.frame = 0;
+            self.step += 1;
+            if self.step >= PATTERN_LENGTH {
+                self.step = 0;
+                self.pattern += 1;
+                if self.pattern >= track.length() {
+                    if let Some(loop_start) = track.loop_start {
+                        self.pattern = loop_start;
+                    }
+                }
+            }
+        }
+    }
+
+    fn play_step(&self, track: &Track) {
+        for (index, channel) in MUSIC_CHANNELS.iter().enumerate() {
+            let note = track.note(index, self.pattern, self.step);
+            if note == REST || self.preempted[index] > 0 {
+                continue;
+            }
+
+            let release = track.frames_per_step / 2;
+            Tone::new()
+                .with_first_frequency(frequency(note))
+                .with_sustain(track.frames_per_step - release)
+                .with_release(release)
+                .with_volume(track.volumes[index] as u32)
+                .with_wave_form(channel.wave_form())
+                .with_duty_cycle(DutyCycle::Half)
+                .play();
+        }
+    }
+}
+
+/// Return the frequency of a note in Hz
+fn frequency(note: Note) -> u16 {
+    // Frequencies of the fourth octave, from C4 (MIDI note 60) to B4
+    const OCTAVE: [u16; 12] = [262, 277, 294, 311, 330, 349, 370, 392, 415, 440, 466, 494];
+
+    let base = OCTAVE[note as usize % 12];
+    let octave = note as i32 / 12 - 5;
+    if octave >= 0 {
+        base << octave
+    } else {
+        base >> -octave
+    }
+}
+
+const C3: Note = 48;
+const D3: Note = 50;
+const E3: Note = 52;
+const F3: Note = 53;
+const G3: Note = 55;
+const A3: Note = 57;
+const B3: Note = 59;
+const C4: Note = 60;
+const D4: Note = 62;
+const E4: Note = 64;
+const F4: Note = 65;
+const G4: Note = 67;
+const A4: Note = 69;
+const B4: Note = 71;
+const C5: Note = 72;
+const D5: Note = 74;
+const E5: Note = 76;
+const __: Note = REST;
+
+#[rustfmt::skip]
+const MENU_MELODY_A: Pattern = [C5, __, G4, __, E4, __, G4, __, A4, __, G4, __, E4, __, D4, __];
+#[rustfmt::skip]
+const MENU_MELODY_B: Pattern = [C5, __, G4, __, E4, __, G4, __, F4, __, E4, __, D4, __, C4, __];
+#[rustfmt::skip]
+const MENU_BASS_A: Pattern = [C3, __, __, __, G3, __, __, __, A3, __, __, __, G3, __, __, __];
+#[rustfmt::skip]
+const MENU_BASS_B: Pattern = [F3, __, __, __, G3, __, __, __, C3, __, __, __, C3, __, __, __];
+
+const MENU_TRACK: Track = Track {
+    frames_per_step: 9,
+    loop_start: Some(0),
+    volumes: [20, 0, 30],
+    sequences: [
+        &[&MENU_MELODY_A, &MENU_MELODY_B],
+        &[],
+        &[&MENU_BASS_A, &MENU_BASS_B],
+    ],
+};
+
+#[rustfmt::skip]
+const GAMEPLAY_MELODY_A: Pattern = [A4, __, C5, A4, E5, __, C5, __, D5, __, B4, G4, B4, __, __, __];
+#[rustfmt::skip]
+const GAMEPLAY_MELODY_B: Pattern = [A4, __, C5, A4, E5, __, D5, C5, B4, __, G4, B4, A4, __, __, __];
+#[rustfmt::skip]
+const GAMEPLAY_HARMONY: Pattern = [E4, __, __, __, E4, __, __, __, D4, __, __, __, D4, __, __, __];
+#[rustfmt::skip]
+const GAMEPLAY_BASS_A: Pattern = [A3, __, A3, __, E3, __, E3, __, G3, __, G3, __, D3, __, D3, __];
+#[rustfmt::skip]
+const GAMEPLAY_BASS_B: Pattern = [A3, __, A3, __, E3, __, E3, __, G3, __, E3, __, A3, __, A3, __];
+
+const GAMEPLAY_TRACK: Track = Track {
+    frames_per_step: 7,
+    loop_start: Some(0),
+    volumes: [15, 10, 25],
+    sequences: [
+        &[&GAMEPLAY_MELODY_A, &GAMEPLAY_MELODY_B],
+        &[&GAMEPLAY_HARMONY],
+        &[&GAMEPLAY_BASS_A, &GAMEPLAY_BASS_B],
+    ],
+};
+
+#[rustfmt::skip]
+const GAME_OVER_MELODY: Pattern = [G4, __, F4, __, E4, __, D4, __, C4, __, B3, __, C4, __, __, __];
+#[rustfmt::skip]
+const GAME_OVER_BASS: Pattern = [C3, __, __, __, G3, __, __, __, F3, __, G3, __, C3, __, __, __];
+
+const GAME_OVER_TRACK: Track = Track {
+    frames_per_step: 12,
+    loop_start: None,
+    volumes: [25, 0, 30],
+    sequences: [&[&GAME_OVER_MELODY], &[], &[&GAME_OVER_BASS]],
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frequency() {
+        assert_eq!(frequency(A4), 440);
+        assert_eq!(frequency(A3), 220);
+        assert_eq!(frequency(C5), 524);
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Random number generation
+
+use core::ops::Range;
+
+use rand_core::{RngCore, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use wasm4fun_log::debug;
+use wasm4fun_time::Ticker;
+
+/// A random number generator
+///
+/// Unlike the generator from `wasm4fun_random`, the internal state of this
+/// one can be serialized, so that it is restored exactly together with the
+/// rest of the game state.
+#[derive(Debug)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct Generator(XorShiftRng);
+
+impl Generator {
+    /// Create a new generator seeded from the current frame number
+    ///
+    /// Since the frame number depends on when the user pressed a button, the
+    /// seed is practically unpredictable.
+    pub fn new_from_user_interaction() -> Self {
+        let frames_from_beginning: u64 = Ticker.since_startup();
+        let seed = frames_from_beginning;
+
+        debug!("Initializing random generator with seed {}", seed);
+        Self::new(seed)
+    }
+
+    /// Create a new generator from a seed
+    pub fn new(seed: u64) -> Self {
+        Self(XorShiftRng::seed_from_u64(seed))
+    }
+
+    /// Generate a random number in a range
+    pub fn gen_range(&mut self, range: Range<i32>) -> i32 {
+        let range_length = (range.end - range.start) as u32;
+        let random_u32 = self.0.next_u32();
+        let random_ranged = random_u32 % range_length;
+        random_ranged as i32 + range.start
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Quick-save and quick-load of the whole state machine
+//!
+//! This module is only meant for development and bug reproduction.
+//!
+//! * Pressing X + Z + Up saves a snapshot of the state machine in memory, and
+//!   dumps it as hexadecimal text to the debug log;
+//! * Pressing X + Z + Down restores the last snapshot saved in memory;
+//! * If the cartridge is built with environment variable `WASMANOID_SNAPSHOT`
+//!   pointing to a file containing such a hexadecimal dump, the snapshot is
+//!   restored when the game starts.
+
+use wasm4fun_input::GamePad;
+use wasm4fun_log::debug;
+
+use crate::debugoverlay::dump_hex;
+use crate::statemachine::Machine;
+
+/// The maximal size of a serialized snapshot
+pub const SNAPSHOT_SIZE: usize = 2048;
+
+/// A snapshot embedded at build time, possibly empty
+static EMBEDDED_SNAPSHOT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/snapshot.bin"));
+
+/// A quick-save slot
+pub struct QuickSave {
+    buffer: [u8; SNAPSHOT_SIZE],
+    length: usize,
+    chord_pressed: bool,
+}
+
+impl QuickSave {
+    /// Create an empty quick-save slot
+    pub const fn new() -> Self {
+        Self {
+            buffer: [0; SNAPSHOT_SIZE],
+            length: 0,
+            chord_pressed: false,
+        }
+    }
+
+    /// Restore the snapshot embedded at build time, if any
+    pub fn load_embedded(&self, machine: &mut Machine) {
+        if EMBEDDED_SNAPSHOT.is_empty() {
+            return;
+        }
+
+        debug!("Restoring embedded snapshot");
+        restore(EMBEDDED_SNAPSHOT, machine);
+    }
+
+    /// Save or load a snapshot when the corresponding chord is pressed
+    pub fn update(&mut self, gamepad: &GamePad, machine: &mut Machine) {
+        let modifiers = gamepad.x() && gamepad.z();
+        let save = modifiers && gamepad.up();
+        let load = modifiers && gamepad.down();
+
+        if (save || load) && !self.chord_pressed {
+            if save {
+                self.save(machine);
+            } else {
+                self.load(machine);
+            }
+        }
+
+        self.chord_pressed = save || load;
+    }
+
+    fn save(&mut self, machine: &Machine) {
+        match machine.save_snapshot(&mut self.buffer) {
+            Ok(bytes) => {
+                self.length = bytes.len();
+                debug!("Snapshot of {} bytes:", self.length);
+                dump_hex(&self.buffer[..self.length]);
+            }
+            Err(error) => {
+                debug!("Could not save snapshot: {}", error);
+            }
+        }
+    }
+
+    fn load(&self, machine: &mut Machine) {
+        if self.length == 0 {
+            debug!("No snapshot to load");
+            return;
+        }
+
+        restore(&self.buffer[..self.length], machine);
+    }
+}
+
+fn restore(bytes: &[u8], machine: &mut Machine) {
+    match Machine::load_snapshot(bytes) {
+        Ok(restored) => {
+            debug!("Restored snapshot of {} bytes", bytes.len());
+            *machine = restored;
+        }
+        Err(error) => {
+            debug!("Could not load snapshot: {}", error);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Visual effects played when switching between states
+
+#[cfg(target_family = "wasm")]
+use micromath::F32Ext;
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use wasm4fun_graphics::{draw_horizontal_line, draw_rect, SCREEN_SIZE};
+
+use crate::graphics::{restore_palette, set_faded_palette};
+use crate::theme::Role;
+
+/// A visual effect played when a state is entered or exited
+///
+/// Each effect lasts for the given number of frames.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub enum Effect {
+    /// No effect, the state is switched instantly
+    None,
+
+    /// Fade the palette to its darkest colour
+    Fade(u8),
+
+    /// Cover the screen from left to right
+    Wipe(u8),
+
+    /// Cover the screen with a shrinking circle
+    Iris(u8),
+}
+
+impl Effect {
+    /// Return the number of frames of the effect
+    pub fn frames(&self) -> u8 {
+        match self {
+            Effect::None => 0,
+            Effect::Fade(frames) | Effect::Wipe(frames) | Effect::Iris(frames) => *frames,
+        }
+    }
+}
+
+/// The direction of an effect
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub enum Direction {
+    /// The outgoing state is being covered
+    Out,
+
+    /// The incoming state is being uncovered
+    In,
+}
+
+/// An effect currently being played
+#[derive(Debug)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct ActiveEffect {
+    effect: Effect,
+    direction: Direction,
+    frame: u8,
+}
+
+impl ActiveEffect {
+    /// Start playing an effect
+    pub fn new(effect: Effect, direction: Direction) -> Self {
+        Self {
+            effect,
+            direction,
+            frame: 0,
+        }
+    }
+
+    /// Return the direction of the effect
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Advance the effect by one frame
+    pub fn update(&mut self) {
+        self.frame = self.frame.saturating_add(1);
+    }
+
+    /// Check whether the effect is over
+    pub fn is_done(&self) -> bool {
+        self.frame >= self.effect.frames()
+    }
+
+    /// Reset any global change made by the effect
+    pub fn finish(&self) {
+        if let Effect::Fade(_) = self.effect {
+            restore_palette();
+        }
+    }
+
+    /// Draw the effect on top of the current frame
+    pub fn draw(&self) {
+        let coverage = self.coverage();
+
+        match self.effect {
+            Effect::None => {}
+            Effect::Fade(_) => set_faded_palette(coverage),
+            Effect::Wipe(_) => draw_wipe(coverage, self.direction),
+            Effect::Iris(_) => draw_iris(coverage),
+        }
+    }
+
+    /// Return how much of the screen is covered, from 0 to 1
+    fn coverage(&self) -> f32 {
+        let frames = self.effect.frames().max(1) as f32;
+        let progress = (self.frame as f32 / frames).clamp(0.0, 1.0);
+        match self.direction {
+            Direction::Out => progress,
+            Direction::In => 1.0 - progress,
+        }
+    }
+}
+
+fn draw_wipe(coverage: f32, direction: Direction) {
+    let width = (coverage * SCREEN_SIZE as f32) as u32;
+    if width == 0 {
+        return;
+    }
+
+    // The cover enters from the left and leaves to the right
+    let x = match direction {
+        Direction::Out => 0,
+        Direction::In => (SCREEN_SIZE - width) as i32,
+    };
+
+    Role::Backdrop.set();
+    draw_rect(x, 0, width, SCREEN_SIZE);
+}
+
+fn draw_iris(coverage: f32) {
+    let center = SCREEN_SIZE as f32 / 2.0;
+
+    // Distance from center to corner
+    let max_radius = center * 1.5;
+    let radius = max_radius * (1.0 - coverage);
+
+    Role::Shade.set();
+
+    for y in 0..SCREEN_SIZE {
+        let dy = y as f32 + 0.5 - center;
+        let half_chord = if dy.abs() < radius {
+            (radius * radius - dy * dy).sqrt()
+        } else {
+            0.0
+        };
+
+        let left = (center - half_chord).max(0.0) as u32;
+        let right = (center + half_chord).min(SCREEN_SIZE as f32) as u32;
+        if left > 0 {
+            draw_horizontal_line(0, y as i32, left);
+        }
+        if right < SCREEN_SIZE {
+            draw_horizontal_line(right as i32, y as i32, SCREEN_SIZE - right);
+        }
+    }
+}
@@ -0,0 +1,66 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use wasm4fun_graphics::{draw_centered_4x4_text, draw_rect, SCREEN_SIZE};
+use wasm4fun_input::GamePad;
+
+use crate::audio::AudioQueue;
+use crate::font::draw_centered_5x8_text;
+use crate::theme::Role;
+
+use super::{GameState, InitialState, MachineError, State, Transition};
+
+const MAX_COOLDOWN: u8 = 30;
+
+/// A state reporting an error in the state machine
+///
+/// The player can restart the game from the initial state.
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct ErrorState {
+    error: MachineError,
+    cooldown: u8,
+}
+
+impl ErrorState {
+    pub fn new(error: MachineError) -> Self {
+        Self {
+            error,
+            cooldown: MAX_COOLDOWN,
+        }
+    }
+}
+
+impl GameState for ErrorState {
+    fn name(&self) -> &'static str {
+        "error"
+    }
+
+    fn draw(&self, _gamepad: Option<&GamePad>) {
+        Role::Backdrop.set();
+        draw_rect(0, 0, SCREEN_SIZE, SCREEN_SIZE);
+
+        Role::Accent.set();
+        draw_centered_5x8_text("SOMETHING WENT WRONG", 48);
+
+        Role::Muted.set();
+        draw_centered_4x4_text(self.error.message(), 72);
+        draw_centered_4x4_text("PRESS Z TO RESTART", 120);
+    }
+
+    fn update(&mut self, gamepad: &GamePad, _audio_queue: &mut AudioQueue) -> Transition {
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+            Transition::Noop
+        } else if gamepad.z() {
+            Transition::Replace(State::Initial(InitialState::new()))
+        } else {
+            Transition::Noop
+        }
+    }
+}
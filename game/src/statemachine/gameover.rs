@@ -0,0 +1,106 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use wasm4fun_graphics::{draw_centered_4x4_text, draw_rect, SCREEN_SIZE};
+use wasm4fun_input::GamePad;
+
+use crate::audio::{AudioQueue, Sfx};
+use crate::font::{draw_centered_5x8_text, LINE_HEIGHT};
+use crate::game::{draw_stats_row, Leaderboard, SaveData, Stats};
+use crate::music::Music;
+use crate::theme::Role;
+
+use super::{Effect, GameState, SaveScoreState, State, Transition};
+
+const MAX_COOLDOWN: u8 = 60;
+
+/// A screen showing the statistics of a finished run
+///
+/// If the score enters the leaderboard, the high-score entry follows.
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct GameOverState {
+    leaderboard: Leaderboard,
+    score: u32,
+    stats: Stats,
+    won: bool,
+    cooldown: u8,
+}
+
+impl GameOverState {
+    pub fn new(leaderboard: Leaderboard, score: u32, stats: Stats, won: bool) -> Self {
+        Self {
+            leaderboard,
+            score,
+            stats,
+            won,
+            cooldown: MAX_COOLDOWN,
+        }
+    }
+}
+
+impl GameState for GameOverState {
+    fn name(&self) -> &'static str {
+        "game_over"
+    }
+
+    fn music(&self) -> Option<Music> {
+        Some(Music::GameOver)
+    }
+
+    /// Wipe the screen open after the game
+    fn enter_effect(&self) -> Effect {
+        Effect::Wipe(20)
+    }
+
+    fn draw(&self, _gamepad: Option<&GamePad>) {
+        Role::Backdrop.set();
+        draw_rect(0, 0, SCREEN_SIZE, SCREEN_SIZE);
+
+        Role::Accent.set();
+        let title = if self.won {
+            "ALL ROUNDS CLEAR!"
+        } else {
+            "GAME OVER"
+        };
+        draw_centered_5x8_text(title, 24);
+
+        draw_stats_row("SCORE", self.score, 48);
+        self.stats.draw(48 + LINE_HEIGHT * 3 / 2);
+
+        if self.cooldown == 0 {
+            Role::Muted.set();
+            draw_centered_4x4_text("PRESS Z TO CONTINUE", SCREEN_SIZE as i32 - 20);
+        }
+    }
+
+    fn update(&mut self, gamepad: &GamePad, audio_queue: &mut AudioQueue) -> Transition {
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+            return Transition::Noop;
+        }
+
+        if !gamepad.z() {
+            return Transition::Noop;
+        }
+
+        audio_queue.play(Sfx::MenuSelect.effect());
+        let is_high_score = SaveData::load()
+            .highscores()
+            .get(self.leaderboard)
+            .is_beated_by(self.score);
+        if is_high_score {
+            Transition::Replace(State::SaveScore(SaveScoreState::new(
+                self.leaderboard,
+                self.score,
+            )))
+        } else {
+            Transition::Pop
+        }
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use wasm4fun_fmt::format_i32_padded;
+use wasm4fun_graphics::{draw_centered_4x4_text, draw_rect, SCREEN_SIZE};
+use wasm4fun_input::GamePad;
+
+use crate::audio::{AudioQueue, Sfx};
+use crate::font::{
+    draw_5x8_text, draw_aligned_5x8_text, draw_centered_5x8_text, text_width, Alignment,
+};
+use crate::game::{Leaderboard, SaveData};
+use crate::graphics::dim_screen;
+use crate::music::Music;
+use crate::theme::Role;
+
+use super::{GameState, Transition};
+
+const MAX_COOLDOWN: u8 = 10;
+
+/// A state for browsing the highscores of all leaderboards
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct HighScoresState {
+    data: SaveData,
+    leaderboard: Leaderboard,
+    cooldown: u8,
+}
+
+impl HighScoresState {
+    pub fn new(leaderboard: Leaderboard) -> Self {
+        Self {
+            data: SaveData::default(),
+            leaderboard,
+            cooldown: MAX_COOLDOWN,
+        }
+    }
+}
+
+impl GameState for HighScoresState {
+    fn name(&self) -> &'static str {
+        "high_scores"
+    }
+
+    fn music(&self) -> Option<Music> {
+        Some(Music::Menu)
+    }
+
+    /// Load the latest high scores
+    fn on_enter(&mut self) {
+        self.data = SaveData::load();
+        self.cooldown = MAX_COOLDOWN;
+    }
+
+    /// The browser is drawn as a panel over the dimmed main menu
+    fn is_opaque(&self) -> bool {
+        false
+    }
+
+    fn draw(&self, _gamepad: Option<&GamePad>) {
+        dim_screen();
+
+        Role::Panel.set();
+        draw_rect(8, 8, SCREEN_SIZE - 16, SCREEN_SIZE - 16);
+
+        Role::Accent.set();
+        draw_centered_5x8_text("HIGH SCORES", 16);
+
+        Role::Muted.set();
+        draw_centered_4x4_text(self.leaderboard.mode().name(), 32);
+        draw_centered_5x8_text(self.leaderboard.difficulty().name(), 40);
+        draw_5x8_text("<", 16, 40);
+        draw_5x8_text(">", SCREEN_SIZE as i32 - 16 - 5, 40);
+
+        let highscores = self.data.highscores().get(self.leaderboard);
+        if highscores.is_empty() {
+            Role::Accent.set();
+            draw_centered_5x8_text("No scores yet", 72);
+        }
+
+        let mut buffer = [0; 10];
+
+        let name_width = 20;
+        let score_width = text_width("00000");
+        let x = (SCREEN_SIZE - name_width - score_width) as i32 / 2;
+        let y = 60;
+
+        for (i, highscore) in highscores.iter().enumerate() {
+            Role::Accent.set();
+            draw_5x8_text(highscore.name(), x, y + i as i32 * 10);
+
+            let text = format_i32_padded(&mut buffer, highscore.score() as i32, 5, ' ');
+            Role::Text.set();
+            draw_aligned_5x8_text(
+                text,
+                x + name_width as i32,
+                y + i as i32 * 10,
+                score_width,
+                Alignment::Right,
+            );
+        }
+
+        Role::Muted.set();
+        draw_centered_4x4_text("PRESS X TO RETURN", SCREEN_SIZE as i32 - 20);
+    }
+
+    fn update(&mut self, gamepad: &GamePad, audio_queue: &mut AudioQueue) -> Transition {
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+            return Transition::Noop;
+        }
+
+        if gamepad.x() {
+            audio_queue.play(Sfx::MenuSelect.effect());
+            Transition::Pop
+        } else if gamepad.left() {
+            self.leaderboard = self.leaderboard.previous();
+            audio_queue.play(Sfx::MenuMove.effect());
+            self.cooldown = MAX_COOLDOWN;
+            Transition::Noop
+        } else if gamepad.right() {
+            self.leaderboard = self.leaderboard.next();
+            audio_queue.play(Sfx::MenuMove.effect());
+            self.cooldown = MAX_COOLDOWN;
+            Transition::Noop
+        } else {
+            Transition::Noop
+        }
+    }
+}
@@ -6,139 +6,310 @@
 
 #![allow(clippy::identity_op, clippy::erasing_op)]
 
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
 use tinyvec::array_vec;
 use tinyvec::ArrayVec;
 
-use wasm4fun_fmt::format_i32_padded;
-use wasm4fun_graphics::{draw_rect, set_drawing_colors, Rotation, SCREEN_SIZE};
+use wasm4fun_fmt::{format_i32, format_i32_padded};
+#[cfg(feature = "debug")]
+use wasm4fun_graphics::draw_line;
+use wasm4fun_graphics::{draw_4x4_text, draw_rect, Rotation, SCREEN_SIZE};
 use wasm4fun_input::GamePad;
 use wasm4fun_log::debug;
-use wasm4fun_random::Generator;
-use wasm4fun_time::{Ticker, Timer};
-
-use crate::assets::{BONUS_SPRITE, BRICK_SPRITE, WALL_SPRITE};
-use crate::audio::{bonus_tone, game_over_tone, game_won_tone, AudioQueue};
-use crate::game::{Ball, Bar, Bonus, Brick, HighScores};
-use crate::graphics::draw_5x8_text;
+use wasm4fun_time::Ticker;
+
+use crate::assets::{animations, BONUS_SPRITE, WALL_SPRITE};
+use crate::audio::{AudioQueue, Sfx};
+#[cfg(feature = "debug")]
+use crate::debugoverlay::{Cheat, LINE_HEIGHT};
+use crate::font::{draw_5x8_text, draw_centered_5x8_text, text_width};
+use crate::game::{
+    Ball, Bar, Bonus, Brick, Camera, CampaignSnapshot, Combo, Difficulty, GameMode, Hud,
+    Leaderboard, Level, Particles, Playfield, SaveData, Stats, MAX_BRICKS,
+};
+#[cfg(feature = "debug")]
+use crate::graphics::draw_numbers;
 use crate::math::normalize_vector;
+use crate::music::Music;
+use crate::random::Generator;
+use crate::theme::Role;
 use crate::ELAPSED_TIME_IN_SECONDS;
 
-use super::{SaveScoreState, State, Transition};
+use super::{
+    Effect, GameOverState, GameState, LevelCompleteState, LevelIntroState, State, Transition,
+};
 
-const LEFT_WALL: i32 = 8;
-const TOP_WALL: i32 = 16;
-const TOP_MARGIN: i32 = 8;
-const BAR_Y: i32 = SCREEN_SIZE as i32 - 30;
-const BAR_HEIGHT: u32 = 6;
 const MAX_BAR_SPEED: f32 = 200.0;
-const MAX_BRICKS: usize = 30;
-const BRICK_WIDTH: u32 = 24;
-const BRICK_HEIGHT: u32 = 8;
 const BRICK_INITIAL_HEALTH: u8 = 3;
 const MAX_BONUSES: usize = 3;
 const BONUS_SPEED: f32 = 100.0;
 const BONUS_WIDTH: u32 = 8;
 const BONUS_HEIGHT: u32 = 8;
-const MAX_BAR_SECTIONS: u32 = 4;
-
+const HIT_SHAKE: f32 = 0.1;
+const DESTROY_SHAKE: f32 = 0.3;
+const DESTROY_HIT_STOP: u8 = 3;
+const BALL_LOST_SHAKE: f32 = 0.8;
+const BALL_LOST_HIT_STOP: u8 = 6;
+const BANNER_Y: i32 = 84;
+const LABEL_SPACING: i32 = 2;
+
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
 pub struct InGameState {
+    leaderboard: Leaderboard,
+    level: u8,
+    lives: u8,
     score: u32,
-    timer: Timer,
+    level_stats: Stats,
+    run_stats: Stats,
+    intro_pending: bool,
+    level_complete: bool,
 
     bar: Bar,
     ball: Ball,
+    combo: Combo,
 
     generator: Generator,
 
+    playfield: Playfield,
     bricks: ArrayVec<[Brick; MAX_BRICKS]>,
     bonuses: ArrayVec<[Bonus; MAX_BONUSES]>,
+    particles: Particles,
+    ball_trail: bool,
+    camera: Camera,
+    hud: Hud,
+
+    #[cfg(feature = "debug")]
+    invincible_floor: bool,
 }
 
 impl InGameState {
-    pub fn new(mut generator: Generator) -> Self {
-        let initial_ball_unit_velocity =
-            normalize_vector((generator.gen_range(-1..1) as f32, -1.0));
-        Self {
+    pub fn new(generator: Generator, mode: GameMode, difficulty: Difficulty, level: u8) -> Self {
+        let playfield = Level::get(level).playfield();
+        let mut state = Self {
+            leaderboard: Leaderboard::new(mode, difficulty),
+            level,
+            lives: mode.initial_lives(),
             score: 0,
-            timer: Timer::new(),
-            bar: Bar::new(),
-            ball: Ball::new(initial_ball_unit_velocity),
+            level_stats: Stats::default(),
+            run_stats: Stats::default(),
+            intro_pending: false,
+            level_complete: false,
+            bar: Bar::new(&playfield),
+            ball: Ball::new((0.0, -1.0), difficulty.ball_speed(), &playfield),
+            combo: Combo::default(),
             generator,
-            bricks: array_vec!(
-                [Brick; MAX_BRICKS] =>
-                // First row
-                (0 * BRICK_WIDTH as u8, 0 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-                (1 * BRICK_WIDTH as u8, 0 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-                (2 * BRICK_WIDTH as u8, 0 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-                (3 * BRICK_WIDTH as u8, 0 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-                (4 * BRICK_WIDTH as u8, 0 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-                (5 * BRICK_WIDTH as u8, 0 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-
-                // Second row
-                (0 * BRICK_WIDTH as u8, 1 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-                (1 * BRICK_WIDTH as u8, 1 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-                (2 * BRICK_WIDTH as u8, 1 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-                (3 * BRICK_WIDTH as u8, 1 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-                (4 * BRICK_WIDTH as u8, 1 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-                (5 * BRICK_WIDTH as u8, 1 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-
-                // Third row
-                (0 * BRICK_WIDTH as u8, 2 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-                (1 * BRICK_WIDTH as u8, 2 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-                (2 * BRICK_WIDTH as u8, 2 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-                (3 * BRICK_WIDTH as u8, 2 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-                (4 * BRICK_WIDTH as u8, 2 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-                (5 * BRICK_WIDTH as u8, 2 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-
-                // Fourth row
-                (0 * BRICK_WIDTH as u8, 3 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-                (1 * BRICK_WIDTH as u8, 3 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-                (2 * BRICK_WIDTH as u8, 3 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-                (3 * BRICK_WIDTH as u8, 3 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-                (4 * BRICK_WIDTH as u8, 3 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-                (5 * BRICK_WIDTH as u8, 3 * BRICK_HEIGHT as u8, BRICK_INITIAL_HEALTH),
-            ),
+            playfield,
+            bricks: array_vec!(),
             bonuses: array_vec!(),
+            particles: Particles::default(),
+            ball_trail: false,
+            camera: Camera::default(),
+            hud: Hud::default(),
+            #[cfg(feature = "debug")]
+            invincible_floor: false,
+        };
+        state.start_level(level);
+        state
+    }
+
+    /// Resume a campaign run from a snapshot
+    pub fn resume(generator: Generator, snapshot: CampaignSnapshot) -> Self {
+        let mut state = Self {
+            leaderboard: Leaderboard::new(GameMode::Campaign, snapshot.difficulty),
+            level: snapshot.level,
+            lives: snapshot.lives,
+            score: snapshot.score,
+            level_stats: Stats::default(),
+            run_stats: Stats::default(),
+            intro_pending: false,
+            level_complete: false,
+            bar: snapshot.bar,
+            ball: snapshot.ball,
+            combo: Combo::default(),
+            generator,
+            playfield: Level::get(snapshot.level).playfield(),
+            bricks: array_vec!(),
+            bonuses: array_vec!(),
+            particles: Particles::default(),
+            ball_trail: false,
+            camera: Camera::default(),
+            hud: Hud::default(),
+            #[cfg(feature = "debug")]
+            invincible_floor: false,
+        };
+        state.start_level(snapshot.level);
+        state
+    }
+
+    /// Fill all bonuses and particles, and max out the score
+    ///
+    /// This gives the largest snapshot of the state.
+    #[cfg(test)]
+    pub fn fill(&mut self) {
+        self.score = u32::MAX;
+        while self.bonuses.len() < self.bonuses.capacity() {
+            self.bonuses.push((u8::MAX, u8::MAX));
+        }
+        // Spawn more pop-ups than the particle system holds
+        for _ in 0..u8::MAX {
+            self.particles.spawn_score((0.0, 0.0), u16::MAX);
         }
     }
 
-    pub fn draw(&self, _gamepad: Option<&GamePad>) {
-        self.clear_background();
-        self.draw_dashboard();
-        self.draw_walls();
-        self.draw_bar();
-        self.draw_ball();
-        self.draw_bonuses();
-        self.draw_bricks();
+    fn start_level(&mut self, level: u8) {
+        debug!("Starting level {}", level);
+        self.level = level;
+        self.playfield = Level::get(level).playfield();
+        self.bricks = Level::get(level).bricks();
+        self.bonuses.clear();
+        self.combo.clear();
+        self.particles.clear();
+        self.camera.reset();
+        self.level_stats = Stats::default();
+        self.intro_pending = true;
+        self.level_complete = false;
+        self.bar.recenter(&self.playfield);
+        self.reset_ball();
+        self.save_progress();
+    }
+
+    /// Apply the player theme, possibly overridden by the level
+    fn apply_theme(&self) {
+        let theme = SaveData::load().settings().theme();
+        theme.with_override(Level::get(self.level).theme()).apply();
+    }
+
+    fn reset_ball(&mut self) {
+        let initial_ball_unit_velocity =
+            normalize_vector((self.generator.gen_range(-1..1) as f32, -1.0));
+        self.ball.reset(initial_ball_unit_velocity, &self.playfield);
+    }
+
+    fn snapshot(&self) -> CampaignSnapshot {
+        CampaignSnapshot {
+            difficulty: self.leaderboard.difficulty(),
+            level: self.level,
+            score: self.score,
+            lives: self.lives,
+            bar: self.bar.clone(),
+            ball: self.ball.clone(),
+        }
+    }
+
+    fn save_progress(&self) {
+        if self.leaderboard.mode() != GameMode::Campaign {
+            return;
+        }
+
+        let mut data = SaveData::load();
+        let progress = data.progress_mut();
+        progress.unlock(self.level);
+        progress.set_snapshot(Some(self.snapshot()));
+        data.save();
+    }
+
+    /// Record the lives left in the snapshot of the current run
+    fn save_lives(&self) {
+        if self.leaderboard.mode() != GameMode::Campaign {
+            return;
+        }
+
+        let mut data = SaveData::load();
+        if let Some(snapshot) = data.progress_mut().snapshot_mut() {
+            snapshot.lives = self.lives;
+            data.save();
+        }
     }
 
     fn clear_background(&self) {
-        set_drawing_colors(0x44);
+        Role::Backdrop.set();
         draw_rect(0, 0, SCREEN_SIZE, SCREEN_SIZE);
     }
 
+    /// Draw the animated background of the level inside the walls
+    fn draw_background(&self) {
+        let (x, y) = self.playfield.to_screen(0, 0);
+        let (width, height) = (self.playfield.width(), self.playfield.height());
+        Level::get(self.level)
+            .background()
+            .draw(x, y, width, height);
+    }
+
     fn draw_dashboard(&self) {
-        set_drawing_colors(0x01);
+        Role::Text.set();
 
         let mut buffer = [0; 10];
 
         let label = "SCORE:";
         let score_digits = 6;
         draw_5x8_text(label, 0, 0);
-        let text = format_i32_padded(&mut buffer, self.score as i32, score_digits, ' ');
-        draw_5x8_text(text, label.len() as i32 * 5, 0);
+        let score = self.hud.displayed_score();
+        let text = format_i32_padded(&mut buffer, score as i32, score_digits, ' ');
+        draw_5x8_text(text, text_width(label) as i32 + LABEL_SPACING, 0);
+
+        let x = 76;
+        if self.leaderboard.mode() == GameMode::Campaign {
+            draw_4x4_text("L", x, 2);
+            let text = format_i32(&mut buffer, self.level as i32 + 1);
+            draw_4x4_text(text, x + 4, 2);
+        }
+        draw_4x4_text("x", x + 14, 2);
+        let text = format_i32(&mut buffer, self.lives as i32);
+        draw_4x4_text(text, x + 18, 2);
 
         let label = "TIME:";
         let time_digits = 4;
-        let x = SCREEN_SIZE as i32 - time_digits as i32 * 5 - label.len() as i32 * 5;
+        let time = self.level_stats.time();
+        let text = format_i32_padded(&mut buffer, time as i32, time_digits, ' ');
+        let x = SCREEN_SIZE as i32 - text_width(text) as i32;
+        draw_5x8_text(text, x, 0);
+        let x = x - LABEL_SPACING - text_width(label) as i32;
         draw_5x8_text(label, x, 0);
-        let text = format_i32_padded(&mut buffer, self.timer.get() as i32, time_digits, ' ');
-        draw_5x8_text(text, label.len() as i32 * 5 + x, 0);
+    }
+
+    fn draw_particles(&self) {
+        self.particles.draw(&self.camera);
+    }
+
+    fn draw_combo(&self) {
+        let hits = self.combo.hits();
+        if hits < 2 {
+            return;
+        }
+
+        Role::Text.set();
+
+        let mut buffer = [0; 10];
+        let y = SCREEN_SIZE as i32 - 8;
+
+        draw_4x4_text("COMBO", 52, y);
+        let text = format_i32(&mut buffer, hits as i32);
+        draw_4x4_text(text, 76, y);
+        draw_4x4_text("x", 92, y);
+        let text = format_i32(&mut buffer, self.combo.multiplier() as i32);
+        draw_4x4_text(text, 96, y);
+    }
+
+    fn draw_high_score_banner(&self) {
+        if !self.hud.is_banner_shown() {
+            return;
+        }
+
+        Role::Panel.set();
+        draw_rect(16, BANNER_Y - 4, SCREEN_SIZE - 32, 16);
+
+        // Blink the text
+        if (self.hud.banner_frames() / 8) % 2 == 1 {
+            Role::Accent.set();
+            draw_centered_5x8_text("NEW HIGH SCORE!", BANNER_Y);
+        }
     }
 
     fn draw_walls(&self) {
-        set_drawing_colors(0x1234);
-        let wall_size = 8;
+        Role::OpaqueSprite.set();
+        let wall_size = Playfield::WALL_TILE_SIZE;
 
         let top_wall = WALL_SPRITE
             .clip(0, 0, wall_size, wall_size)
@@ -152,79 +323,78 @@ impl InGameState {
         let top_right_corner = WALL_SPRITE
             .clip(wall_size, 0, wall_size, wall_size)
             .flip_horizontally(true);
-        top_left_corner.blit(0, TOP_MARGIN);
-        top_right_corner.blit(SCREEN_SIZE as i32 - wall_size as i32, TOP_MARGIN);
-        for i in 1..19 {
-            top_wall.blit(i * wall_size as i32, TOP_MARGIN);
-            left_wall.blit(0, TOP_MARGIN + i * wall_size as i32);
-            right_wall.blit(
-                SCREEN_SIZE as i32 - wall_size as i32,
-                TOP_MARGIN + i * wall_size as i32,
-            );
+
+        let step = wall_size as usize;
+        let wall = self.playfield.wall() as i32;
+        let (left, top) = self.playfield.to_screen(0, 0);
+        let (left, top) = self.camera.translate(left, top);
+        let right = left + self.playfield.width() as i32;
+        // One more row of tiles, so that shaking never uncovers the bottom
+        let bottom = top + self.playfield.height() as i32 + wall_size as i32;
+
+        for y in (top - wall..top).step_by(step) {
+            for x in (left - wall..left).step_by(step) {
+                top_left_corner.blit(x, y);
+            }
+            for x in (left..right).step_by(step) {
+                top_wall.blit(x, y);
+            }
+            for x in (right..right + wall).step_by(step) {
+                top_right_corner.blit(x, y);
+            }
+        }
+        for y in (top..bottom).step_by(step) {
+            for x in (left - wall..left).step_by(step) {
+                left_wall.blit(x, y);
+            }
+            for x in (right..right + wall).step_by(step) {
+                right_wall.blit(x, y);
+            }
         }
-        left_wall.blit(0, TOP_MARGIN + 19 * wall_size as i32);
-        right_wall.blit(
-            SCREEN_SIZE as i32 - wall_size as i32,
-            TOP_MARGIN + 19 * wall_size as i32,
-        );
     }
 
     fn draw_bar(&self) {
-        self.bar.draw();
+        self.bar.draw(&self.camera, &self.playfield);
     }
 
     fn draw_ball(&self) {
-        self.ball.draw();
+        self.ball.draw(&self.camera, &self.playfield);
     }
 
     fn draw_bonuses(&self) {
-        let src_x = (4.0 * (Ticker.within_second() as f32 / 60.0)) as u32 * 8;
+        let src_x = animations::BONUS.animation().frame() * BONUS_WIDTH;
 
         for (x, y) in self.bonuses.iter() {
-            BONUS_SPRITE.clip(src_x, 0, 8, 8).blit(*x as i32, *y as i32);
+            let (x, y) = self.playfield.to_screen(*x as i32, *y as i32);
+            let (x, y) = self.camera.translate(x, y);
+            BONUS_SPRITE
+                .clip(src_x, 0, BONUS_WIDTH, BONUS_HEIGHT)
+                .blit(x, y);
         }
     }
 
     fn draw_bricks(&self) {
-        for (x, y, health) in self.bricks.iter() {
-            set_drawing_colors(0x1230);
-            BRICK_SPRITE
-                .clip(0, 0, BRICK_SPRITE.width(), 8)
-                .blit(*x as i32 + LEFT_WALL, *y as i32 + TOP_WALL);
-            if *health < 3 {
-                BRICK_SPRITE
-                    .clip(0, 8, BRICK_SPRITE.width(), 8)
-                    .blit(*x as i32 + LEFT_WALL, *y as i32 + TOP_WALL);
-            }
-            if *health < 2 {
-                BRICK_SPRITE
-                    .clip(0, 16, BRICK_SPRITE.width(), 8)
-                    .blit(*x as i32 + LEFT_WALL, *y as i32 + TOP_WALL);
-            }
+        for brick in self.bricks.iter() {
+            let (x, y) = brick.position();
+            let (x, y) = self.playfield.to_screen(x as i32, y as i32);
+            let (x, y) = self.camera.translate(x, y);
+            brick.draw(x, y);
         }
     }
 
-    pub fn update(&mut self, gamepad: &GamePad, audio_queue: &mut AudioQueue) -> Transition {
-        self.update_bar_position(gamepad);
-        self.update_ball_position();
-        self.update_bonuses_position();
-        self.handle_collisions(audio_queue);
-        self.catch_bonuses(audio_queue);
-        self.remove_destroyed_bricks();
-        self.remove_lost_bonuses();
-        self.update_timer();
-        self.ensure_ball_moves_vertically();
-        self.normalize_ball_velocity();
-
-        self.handle_game_over(audio_queue)
-    }
-
     fn update_ball_position(&mut self) {
         self.ball.update_position();
     }
 
+    #[cfg(feature = "debug")]
+    fn bounce_ball_on_floor(&mut self) {
+        if self.invincible_floor {
+            self.ball.bounce_on_floor(&self.playfield);
+        }
+    }
+
     fn update_bar_position(&mut self, gamepad: &GamePad) {
-        self.bar.update_position(gamepad);
+        self.bar.update_position(gamepad, &self.playfield);
     }
 
     fn update_bonuses_position(&mut self) {
@@ -234,31 +404,53 @@ impl InGameState {
     }
 
     pub fn handle_collisions(&mut self, audio_queue: &mut AudioQueue) {
-        let (score, bonus) = self
-            .ball
-            .handle_collisions(&self.bar, &mut self.bricks, audio_queue);
+        let (score, destroyed) = self.ball.handle_collisions(
+            &self.playfield,
+            &self.bar,
+            &mut self.bricks,
+            &mut self.combo,
+            audio_queue,
+        );
 
         self.score += score;
+        self.level_stats.record_combo(self.combo.hits());
+        if score > 0 {
+            self.camera.shake(HIT_SHAKE * self.ball.strength() as f32);
+        }
+        self.award_combo_bonus(audio_queue);
 
-        if let Some(bonus) = bonus {
+        if let Some((x, y)) = destroyed {
             if self.bonuses.len() < self.bonuses.capacity() && self.generator.gen_range(0..10) < 5 {
                 debug!("Generating a bonus");
 
-                self.bonuses.push(bonus);
+                let x = x.saturating_sub(BONUS_WIDTH as u8 / 2);
+                let y = y.saturating_sub(BONUS_HEIGHT as u8 / 2);
+                self.bonuses.push((x, y));
+            }
+        }
+    }
+
+    fn award_combo_bonus(&mut self, audio_queue: &mut AudioQueue) {
+        if let Some(hits) = self.combo.take_finished() {
+            let bonus = Combo::bonus(hits);
+            if bonus > 0 {
+                debug!("Combo of {} hits, awarding {} points", hits, bonus);
+                self.score += bonus;
+                audio_queue.play(Sfx::ComboBonus.effect());
             }
         }
     }
 
     fn catch_bonuses(&mut self, audio_queue: &mut AudioQueue) {
-        let bar_x1 = self.bar.position() as i32 - LEFT_WALL;
-        let bar_y1 = BAR_Y;
-        let bar_x2 = bar_x1 + 8 * (self.bar.sections() as i32 + 2);
-        let bar_y2 = bar_y1 + BAR_HEIGHT as i32;
+        let bar_x1 = self.bar.position() as i32;
+        let bar_y1 = self.playfield.bar_y();
+        let bar_x2 = bar_x1 + self.bar.width() as i32;
+        let bar_y2 = bar_y1 + self.bar.height() as i32;
 
         let mut caught_bonuses = 0;
 
         for (x, y) in self.bonuses.iter_mut() {
-            let bonus_x1 = *x as i32 - LEFT_WALL;
+            let bonus_x1 = *x as i32;
             let bonus_y1 = *y as i32;
             let bonus_x2 = bonus_x1 + BONUS_WIDTH as i32;
             let bonus_y2 = bonus_y1 + BONUS_HEIGHT as i32;
@@ -269,9 +461,17 @@ impl InGameState {
                 || (bar_y1 - 1..bar_y2 + 1).contains(&bonus_y2);
             if contained_x && contained_y {
                 debug!("Bonus caught!!!");
-                audio_queue.play(bonus_tone());
+                audio_queue.play(Sfx::Bonus.effect());
+                let (center_x, center_y) = self.playfield.to_screen(
+                    *x as i32 + BONUS_WIDTH as i32 / 2,
+                    *y as i32 + BONUS_HEIGHT as i32 / 2,
+                );
+                let center = (center_x as f32, center_y as f32);
+                self.particles.spawn_sparks(&mut self.generator, center);
+                self.level_stats.count_bonus();
                 caught_bonuses += 1;
-                *y = SCREEN_SIZE as u8 + 10;
+                // Move the bonus below the board, so that it is removed
+                *y = self.playfield.height() as u8;
             }
         }
 
@@ -281,11 +481,11 @@ impl InGameState {
     }
 
     fn give_random_bonus(&mut self) {
-        let bonus_type = if self.bar.sections() < MAX_BAR_SECTIONS
+        let bonus_type = if self.bar.sections() < Bar::MAX_SECTIONS
             && self.ball.strength() < BRICK_INITIAL_HEALTH
         {
             self.generator.gen_range(1..4)
-        } else if self.bar.sections() < MAX_BAR_SECTIONS {
+        } else if self.bar.sections() < Bar::MAX_SECTIONS {
             1
         } else if self.ball.strength() < BRICK_INITIAL_HEALTH {
             2
@@ -309,16 +509,57 @@ impl InGameState {
         }
     }
 
-    fn remove_destroyed_bricks(&mut self) {
-        self.bricks.retain(|(_x, _y, health)| *health > 0);
+    fn destroy_bricks(&mut self) {
+        let points = (100 * self.combo.multiplier()) as u16;
+        for brick in self.bricks.iter().filter(|brick| brick.is_just_destroyed()) {
+            let (x, y) = brick.position();
+            let (center_x, center_y) = self.playfield.to_screen(
+                x as i32 + Brick::WIDTH as i32 / 2,
+                y as i32 + Brick::HEIGHT as i32 / 2,
+            );
+            let center = (center_x as f32, center_y as f32);
+            self.particles.spawn_debris(&mut self.generator, center);
+            self.particles.spawn_score(center, points);
+            self.level_stats.count_brick();
+            self.camera.shake(DESTROY_SHAKE);
+            self.camera.hit_stop(DESTROY_HIT_STOP);
+        }
+    }
+
+    /// Advance the brick animations and remove the bricks that shrank away
+    fn update_bricks(&mut self) {
+        for brick in self.bricks.iter_mut() {
+            brick.update();
+        }
+        self.bricks.retain(|brick| !brick.is_gone());
+    }
+
+    fn update_particles(&mut self) {
+        if self.ball_trail {
+            let (x, y) = self.ball.coordinates();
+            let (x, y) = self.playfield.to_screen(x as i32, y as i32);
+            self.particles.spawn_trail((x as f32, y as f32));
+        }
+        self.particles.update();
+    }
+
+    fn update_hud(&mut self) {
+        self.hud.update(self.score);
+    }
+
+    fn update_camera(&mut self) {
+        self.camera.update(&mut self.generator);
     }
 
     fn remove_lost_bonuses(&mut self) {
-        self.bonuses.retain(|(_x, y)| *y < SCREEN_SIZE as u8);
+        let height = self.playfield.height();
+        self.bonuses.retain(|(_x, y)| (*y as u32) < height);
     }
 
     fn update_timer(&mut self) {
-        self.timer.update()
+        if Ticker.within_second() == 0 {
+            self.level_stats.tick();
+        }
     }
 
     fn ensure_ball_moves_vertically(&mut self) {
@@ -329,37 +570,245 @@ impl InGameState {
         self.ball.normalize_velocity();
     }
 
-    fn handle_game_over(&self, audio_queue: &mut AudioQueue) -> Transition {
-        let game_over = self.ball_lost();
-        let game_won = self.all_bricks_destroyed();
+    fn handle_game_over(&mut self, audio_queue: &mut AudioQueue) -> Transition {
+        if self.ball_lost() {
+            if self.lives > 1 {
+                debug!("Ball lost, {} lives left", self.lives - 1);
+                audio_queue.play(Sfx::GameOver.effect());
+                self.camera.shake(BALL_LOST_SHAKE);
+                self.camera.hit_stop(BALL_LOST_HIT_STOP);
+                self.lives -= 1;
+                self.save_lives();
+                self.bonuses.clear();
+                self.combo.clear();
+                self.bar.recenter(&self.playfield);
+                self.reset_ball();
+                return Transition::Noop;
+            }
 
-        if game_over {
-            audio_queue.play(game_over_tone());
+            audio_queue.play(Sfx::GameOver.effect());
+            self.run_stats.add(&self.level_stats);
+            return self.end_run(false);
         }
 
-        if game_won {
-            audio_queue.play(game_won_tone());
-            audio_queue.enqueue(10, game_won_tone().with_release(60));
+        if self.all_bricks_destroyed() {
+            // The last combo ends with the level
+            self.combo.end();
+            self.award_combo_bonus(audio_queue);
+
+            audio_queue.play(Sfx::GameWon.effect());
+            audio_queue.enqueue(10, Sfx::GameWonFinal.effect());
+
+            let time_bonus = self.level_stats.time_bonus();
+            debug!("Level completed, awarding a time bonus of {}", time_bonus);
+            self.score += time_bonus;
+            self.run_stats.add(&self.level_stats);
+            self.level_complete = true;
+            return Transition::Push(State::LevelComplete(LevelCompleteState::new(
+                self.level,
+                self.level_stats,
+                time_bonus,
+            )));
         }
 
-        if game_over || game_won {
-            // If in single-player mode, possibly save a high score
-            let highscores = HighScores::load();
-            if highscores.is_beated_by(self.score) {
-                Transition::Replace(State::SaveScore(SaveScoreState::new(self.score)))
-            } else {
-                Transition::Pop
-            }
-        } else {
-            Transition::Noop
+        Transition::Noop
+    }
+
+    /// Start the next level of a campaign, or end the run after the last one
+    fn start_next_level(&mut self) -> Transition {
+        self.level_complete = false;
+
+        let next_level = self.level + 1;
+        if self.leaderboard.mode() == GameMode::Campaign && next_level < Level::count() {
+            self.start_level(next_level);
+            self.apply_theme();
+            return self.push_intro();
         }
+
+        self.end_run(true)
+    }
+
+    /// Announce the level with a countdown
+    fn push_intro(&mut self) -> Transition {
+        self.intro_pending = false;
+        Transition::Push(State::LevelIntro(LevelIntroState::new(self.level)))
+    }
+
+    fn end_run(&self, won: bool) -> Transition {
+        if self.leaderboard.mode() == GameMode::Campaign {
+            debug!("Campaign run is over");
+            let mut data = SaveData::load();
+            data.progress_mut().set_snapshot(None);
+            data.save();
+        }
+
+        Transition::Replace(State::GameOver(GameOverState::new(
+            self.leaderboard,
+            self.score,
+            self.run_stats,
+            won,
+        )))
     }
 
     fn ball_lost(&self) -> bool {
-        self.ball.is_lost()
+        self.ball.is_lost(&self.playfield)
     }
 
+    /// Return whether all bricks are destroyed
+    ///
+    /// Indestructible bricks and bricks still shrinking away are ignored.
     fn all_bricks_destroyed(&self) -> bool {
-        self.bricks.is_empty()
+        !self.bricks.iter().any(|brick| brick.is_remaining())
+    }
+}
+
+impl GameState for InGameState {
+    fn name(&self) -> &'static str {
+        "in_game"
+    }
+
+    fn music(&self) -> Option<Music> {
+        Some(Music::Gameplay)
+    }
+
+    /// Close an iris over the board when the game is over
+    fn exit_effect(&self) -> Effect {
+        Effect::Iris(40)
+    }
+
+    /// Apply the theme of the level, and read the settings and the high score
+    fn on_enter(&mut self) {
+        self.apply_theme();
+        let data = SaveData::load();
+        let high_score = data.highscores().get(self.leaderboard).best();
+        self.hud.set_high_score(high_score, self.score);
+        let settings = *data.settings();
+        self.ball_trail = settings.ball_trail();
+        self.camera
+            .set_enabled(settings.screen_shake(), settings.hit_stop());
+    }
+
+    /// Restore the player theme, which the level might have overridden
+    fn on_exit(&mut self) {
+        SaveData::load().settings().theme().apply();
+    }
+
+    fn draw(&self, _gamepad: Option<&GamePad>) {
+        self.clear_background();
+        self.draw_background();
+        self.draw_dashboard();
+        self.draw_walls();
+        self.draw_bar();
+        self.draw_ball();
+        self.draw_bonuses();
+        self.draw_bricks();
+        self.draw_particles();
+        self.draw_combo();
+        self.draw_high_score_banner();
+    }
+
+    /// Advance the simulation, unless it is frozen by a hit-stop
+    ///
+    /// Every level starts with an intro, and the simulation moves to the next
+    /// level once the statistics of the completed one were shown.
+    fn update(&mut self, gamepad: &GamePad, audio_queue: &mut AudioQueue) -> Transition {
+        if self.level_complete {
+            return self.start_next_level();
+        }
+        if self.intro_pending {
+            return self.push_intro();
+        }
+
+        let frozen = self.camera.is_frozen();
+        self.update_camera();
+        if frozen {
+            return Transition::Noop;
+        }
+
+        self.update_bar_position(gamepad);
+        self.update_ball_position();
+        #[cfg(feature = "debug")]
+        self.bounce_ball_on_floor();
+        self.update_bonuses_position();
+        self.handle_collisions(audio_queue);
+        self.catch_bonuses(audio_queue);
+        self.destroy_bricks();
+        self.update_bricks();
+        self.remove_lost_bonuses();
+        self.update_particles();
+        self.update_hud();
+        self.update_timer();
+        self.ensure_ball_moves_vertically();
+        self.normalize_ball_velocity();
+
+        self.handle_game_over(audio_queue)
+    }
+
+    /// Show brick health, ball position and velocity, and the last contact
+    #[cfg(feature = "debug")]
+    fn draw_debug(&self) {
+        let mut buffer = [0; 4];
+        Role::Ink.set();
+        for brick in self.bricks.iter().filter(|brick| brick.is_remaining()) {
+            let (x, y) = brick.position();
+            let text = format_i32(&mut buffer, brick.health() as i32);
+            let (x, y) = self.playfield.to_screen(x as i32, y as i32);
+            let x = x + Brick::WIDTH as i32 / 2 - 2;
+            let y = y + 2;
+            draw_4x4_text(text, x, y);
+        }
+
+        let (x, y) = self.ball.coordinates();
+        let (vx, vy) = self.ball.velocity();
+        let (x, y) = self.playfield.to_screen(x as i32, y as i32);
+        Role::Text.set();
+        draw_line(x, y, x + (vx * 0.2) as i32, y + (vy * 0.2) as i32);
+
+        if let Some(contact) = self.ball.last_contact() {
+            let (px, py) = contact.point;
+            let (nx, ny) = contact.normal;
+            let (px, py) = self.playfield.to_screen(px as i32, py as i32);
+            Role::Accent.set();
+            draw_line(px, py, px + (nx * 8.0) as i32, py + (ny * 8.0) as i32);
+            draw_rect(px - 1, py - 1, 3, 3);
+        }
+
+        let panel_x = SCREEN_SIZE as i32 / 2;
+        let panel_y = SCREEN_SIZE as i32 - 3 * LINE_HEIGHT - 2;
+        Role::Backdrop.set();
+        draw_rect(
+            panel_x,
+            panel_y,
+            SCREEN_SIZE / 2,
+            3 * LINE_HEIGHT as u32 + 2,
+        );
+
+        Role::Text.set();
+        let panel_x = panel_x + 2;
+        let panel_y = panel_y + 2;
+        let (x, y) = self.playfield.to_board(x, y);
+        draw_numbers("POS", &[x, y], panel_x, panel_y);
+        draw_numbers(
+            "VEL",
+            &[vx as i32, vy as i32],
+            panel_x,
+            panel_y + LINE_HEIGHT,
+        );
+        let floor = if self.invincible_floor {
+            "FLOOR ON"
+        } else {
+            "FLOOR OFF"
+        };
+        draw_4x4_text(floor, panel_x, panel_y + 2 * LINE_HEIGHT);
+    }
+
+    #[cfg(feature = "debug")]
+    fn cheat(&mut self, cheat: Cheat) {
+        match cheat {
+            Cheat::SkipLevel => self.bricks.clear(),
+            Cheat::GrantBonus => self.give_random_bonus(),
+            Cheat::ToggleInvincibleFloor => self.invincible_floor = !self.invincible_floor,
+            Cheat::OpenSfxEditor => {}
+        }
     }
 }
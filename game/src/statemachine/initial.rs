@@ -4,14 +4,20 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
 use tinyvec::array_vec;
 
 use wasm4fun_input::GamePad;
 
 use crate::audio::AudioQueue;
 
-use super::{MainMenuState, SplashScreenState, State, TestIntersectionsState, Transition};
+use super::{
+    GameState, MainMenuState, SplashScreenState, State, TestIntersectionsState, Transition,
+};
 
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
 pub struct InitialState {}
 
 impl InitialState {
@@ -19,10 +25,21 @@ impl InitialState {
     pub fn new() -> Self {
         Self {}
     }
+}
+
+impl GameState for InitialState {
+    fn name(&self) -> &'static str {
+        "initial"
+    }
+
+    /// The initial state does not draw anything
+    fn is_opaque(&self) -> bool {
+        false
+    }
 
-    pub fn draw(&self, _gamepad: Option<&GamePad>) {}
+    fn draw(&self, _gamepad: Option<&GamePad>) {}
 
-    pub fn update(&self, _gamepad: &GamePad, _audio_queue: &mut AudioQueue) -> Transition {
+    fn update(&mut self, _gamepad: &GamePad, _audio_queue: &mut AudioQueue) -> Transition {
         if cfg!(feature = "test-intersections") {
             Transition::Replace(State::TestIntersections(TestIntersectionsState::new()))
         } else {
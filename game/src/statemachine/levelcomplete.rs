@@ -0,0 +1,94 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use wasm4fun_graphics::{draw_centered_4x4_text, draw_rect, SCREEN_SIZE};
+use wasm4fun_input::GamePad;
+
+use crate::audio::{AudioQueue, Sfx};
+use crate::font::{draw_centered_5x8_text, LINE_HEIGHT};
+use crate::game::{draw_stats_row, Stats};
+use crate::graphics::dim_screen;
+use crate::music::Music;
+use crate::theme::Role;
+
+use super::{format_round_title, GameState, Transition};
+
+const MAX_COOLDOWN: u8 = 60;
+
+/// An overlay showing the statistics of a completed level
+///
+/// The time bonus is already added to the score when the overlay is shown.
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct LevelCompleteState {
+    level: u8,
+    stats: Stats,
+    time_bonus: u32,
+    cooldown: u8,
+}
+
+impl LevelCompleteState {
+    pub fn new(level: u8, stats: Stats, time_bonus: u32) -> Self {
+        Self {
+            level,
+            stats,
+            time_bonus,
+            cooldown: MAX_COOLDOWN,
+        }
+    }
+}
+
+impl GameState for LevelCompleteState {
+    fn name(&self) -> &'static str {
+        "level_complete"
+    }
+
+    fn music(&self) -> Option<Music> {
+        Some(Music::Gameplay)
+    }
+
+    /// The statistics are drawn as a panel over the dimmed board
+    fn is_opaque(&self) -> bool {
+        false
+    }
+
+    fn draw(&self, _gamepad: Option<&GamePad>) {
+        dim_screen();
+
+        Role::Panel.set();
+        draw_rect(16, 32, SCREEN_SIZE - 32, 96);
+
+        let mut buffer = [0; 16];
+
+        Role::Accent.set();
+        let title = format_round_title(&mut buffer, self.level, " CLEAR");
+        draw_centered_5x8_text(title, 40);
+
+        let y = self.stats.draw(56);
+        draw_stats_row("TIME BONUS", self.time_bonus, y + LINE_HEIGHT / 2);
+
+        if self.cooldown == 0 {
+            Role::Muted.set();
+            draw_centered_4x4_text("PRESS Z TO CONTINUE", 116);
+        }
+    }
+
+    fn update(&mut self, gamepad: &GamePad, audio_queue: &mut AudioQueue) -> Transition {
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+            return Transition::Noop;
+        }
+
+        if gamepad.z() {
+            audio_queue.play(Sfx::MenuSelect.effect());
+            Transition::Pop
+        } else {
+            Transition::Noop
+        }
+    }
+}
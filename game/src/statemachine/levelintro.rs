@@ -0,0 +1,119 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use wasm4fun_fmt::format_i32;
+use wasm4fun_graphics::{draw_rect, SCREEN_SIZE};
+use wasm4fun_input::GamePad;
+
+use crate::audio::{AudioQueue, Sfx};
+use crate::font::draw_centered_5x8_text;
+use crate::music::Music;
+use crate::theme::Role;
+use crate::FRAMES_PER_SECOND;
+
+use super::{GameState, Transition};
+
+const COUNTDOWN_SECONDS: u16 = 3;
+const PANEL_Y: i32 = 64;
+const PANEL_HEIGHT: u32 = 32;
+
+/// An overlay announcing a level with a countdown
+///
+/// The board is drawn below, but it is not updated until the countdown is
+/// over.
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct LevelIntroState {
+    level: u8,
+    frames: u16,
+}
+
+impl LevelIntroState {
+    pub fn new(level: u8) -> Self {
+        Self {
+            level,
+            frames: COUNTDOWN_SECONDS * FRAMES_PER_SECOND as u16,
+        }
+    }
+
+    /// Return the number of seconds left, rounded up
+    fn seconds_left(&self) -> u16 {
+        self.frames.div_ceil(FRAMES_PER_SECOND as u16)
+    }
+}
+
+/// Format the title of a level, such as `ROUND 3`, followed by a suffix
+///
+/// The buffer must fit the title and the suffix.
+pub fn format_round_title<'a>(buffer: &'a mut [u8], level: u8, suffix: &str) -> &'a str {
+    const LABEL: &[u8] = b"ROUND ";
+
+    buffer[..LABEL.len()].copy_from_slice(LABEL);
+    let length = LABEL.len() + format_i32(&mut buffer[LABEL.len()..], level as i32 + 1).len();
+    buffer[length..length + suffix.len()].copy_from_slice(suffix.as_bytes());
+    core::str::from_utf8(&buffer[..length + suffix.len()]).unwrap_or("")
+}
+
+impl GameState for LevelIntroState {
+    fn name(&self) -> &'static str {
+        "level_intro"
+    }
+
+    fn music(&self) -> Option<Music> {
+        Some(Music::Gameplay)
+    }
+
+    /// The intro is drawn as a panel over the board
+    fn is_opaque(&self) -> bool {
+        false
+    }
+
+    fn draw(&self, _gamepad: Option<&GamePad>) {
+        Role::Panel.set();
+        draw_rect(16, PANEL_Y, SCREEN_SIZE - 32, PANEL_HEIGHT);
+
+        let mut buffer = [0; 16];
+
+        Role::Accent.set();
+        let title = format_round_title(&mut buffer, self.level, "");
+        draw_centered_5x8_text(title, PANEL_Y + 6);
+
+        Role::Text.set();
+        let text = format_i32(&mut buffer, self.seconds_left() as i32);
+        draw_centered_5x8_text(text, PANEL_Y + 18);
+    }
+
+    fn update(&mut self, _gamepad: &GamePad, audio_queue: &mut AudioQueue) -> Transition {
+        if self.frames.is_multiple_of(FRAMES_PER_SECOND as u16) && self.frames > 0 {
+            audio_queue.play(Sfx::MenuMove.effect());
+        }
+
+        self.frames = self.frames.saturating_sub(1);
+        if self.frames == 0 {
+            audio_queue.play(Sfx::MenuSelect.effect());
+            Transition::Pop
+        } else {
+            Transition::Noop
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_title_is_formatted_in_one_piece() {
+        let mut buffer = [0; 16];
+        assert_eq!(format_round_title(&mut buffer, 0, ""), "ROUND 1");
+        assert_eq!(
+            format_round_title(&mut buffer, 11, " CLEAR"),
+            "ROUND 12 CLEAR"
+        );
+    }
+}
@@ -4,27 +4,74 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use wasm4fun_fmt::format_i32_padded;
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use wasm4fun_fmt::{format_i32, format_i32_padded};
 use wasm4fun_graphics::{
-    draw_4x4_text, draw_centered_text, draw_point, draw_rect, set_drawing_colors, SCREEN_SIZE,
+    draw_4x4_text, draw_centered_4x4_text, draw_centered_text, draw_point, draw_rect, SCREEN_SIZE,
 };
 use wasm4fun_input::GamePad;
 use wasm4fun_log::debug;
-use wasm4fun_random::Generator;
 use wasm4fun_time::Ticker;
 
-use crate::audio::AudioQueue;
+use crate::audio::{AudioQueue, Sfx};
 
-use super::{InGameState, State, Transition};
+use super::{Effect, GameState, HighScoresState, InGameState, OptionsState, State, Transition};
 
 const MAX_COOLDOWN: u32 = 10;
 
-use crate::assets::{BUTTON_SPRITE, LOGO_SPRITE};
-use crate::game::HighScores;
-use crate::graphics::draw_5x8_text;
+use crate::assets::{animations, BUTTON_SPRITE, LOGO_SPRITE};
+use crate::font::{draw_5x8_text, draw_wrapped_5x8_text, text_width, Alignment};
+use crate::game::{Difficulty, GameMode, Leaderboard, SaveData};
+use crate::music::Music;
+use crate::random::Generator;
+use crate::theme::Role;
+
+/// An entry in the main menu
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+enum Entry {
+    /// Resume the last campaign run
+    Continue,
+
+    /// Start a new campaign run
+    Campaign,
+
+    /// Start a new classic game
+    Classic,
+
+    /// Change the player settings
+    Options,
+}
+
+impl Entry {
+    fn name(&self) -> &'static str {
+        match self {
+            Entry::Continue => "CONTINUE",
+            Entry::Campaign => "CAMPAIGN",
+            Entry::Classic => "CLASSIC",
+            Entry::Options => "OPTIONS",
+        }
+    }
+}
 
+/// A row in the main menu
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+enum Row {
+    Entry,
+    Difficulty,
+    Level,
+}
+
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
 pub struct MainMenuState {
-    highscores: HighScores,
+    data: SaveData,
+    entry: Entry,
+    row: Row,
+    difficulty: Difficulty,
+    level: u8,
     cooldown: u32,
     step: u32,
 }
@@ -32,25 +79,24 @@ pub struct MainMenuState {
 impl MainMenuState {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        let highscores = HighScores::load();
         Self {
-            highscores,
+            data: SaveData::default(),
+            entry: Entry::Classic,
+            row: Row::Entry,
+            difficulty: Difficulty::default(),
+            level: 0,
             cooldown: MAX_COOLDOWN,
             step: 0,
         }
     }
 
-    pub fn draw(&self, _gamepad: Option<&GamePad>) {
-        self.draw_logo();
-        self.draw_highscores();
-        self.draw_press_button_to_start();
-        self.draw_credits();
-    }
-
-    pub fn update(&mut self, gamepad: &GamePad, _audio_queue: &mut AudioQueue) -> Transition {
-        self.update_cooldowns();
-        self.update_step();
-        self.handle_input(gamepad)
+    /// Create a main menu showing some saved data
+    #[cfg(test)]
+    pub fn with_data(data: SaveData) -> Self {
+        Self {
+            data,
+            ..Self::new()
+        }
     }
 
     fn update_cooldowns(&mut self) {
@@ -66,40 +112,160 @@ impl MainMenuState {
         }
     }
 
-    fn handle_input(&mut self, gamepad: &GamePad) -> Transition {
-        if self.cooldown == 0 && gamepad.z() {
-            // Necessary for when we return to this state
-            self.cooldown = MAX_COOLDOWN;
+    fn handle_input(&mut self, gamepad: &GamePad, audio_queue: &mut AudioQueue) -> Transition {
+        if self.cooldown > 0 {
+            return Transition::Noop;
+        }
 
-            debug!("Start a new game");
-            let generator = Generator::new_from_user_interaction();
-            Transition::Push(State::InGame(InGameState::new(generator)))
+        if gamepad.z() && self.entry == Entry::Options {
+            audio_queue.play(Sfx::MenuSelect.effect());
+            Transition::Push(State::Options(OptionsState::new()))
+        } else if gamepad.z() {
+            self.start_game()
+        } else if gamepad.x() {
+            Transition::Push(State::HighScores(HighScoresState::new(self.leaderboard())))
+        } else if gamepad.up() || gamepad.down() {
+            let rows = self.rows();
+            let count = rows.iter().flatten().count();
+            let index = rows.iter().flatten().position(|row| *row == self.row);
+            let index = index.unwrap_or(0);
+            let index = if gamepad.down() {
+                (index + 1) % count
+            } else {
+                (index + count - 1) % count
+            };
+            self.row = rows
+                .iter()
+                .flatten()
+                .nth(index)
+                .copied()
+                .unwrap_or(Row::Entry);
+            audio_queue.play(Sfx::MenuMove.effect());
+            self.cooldown = MAX_COOLDOWN;
+            Transition::Noop
+        } else if gamepad.left() || gamepad.right() {
+            self.change_row_value(gamepad.right());
+            audio_queue.play(Sfx::MenuMove.effect());
+            self.cooldown = MAX_COOLDOWN;
+            Transition::Noop
         } else {
             Transition::Noop
         }
     }
 
+    fn start_game(&mut self) -> Transition {
+        let generator = Generator::new_from_user_interaction();
+        let state = match (self.entry, self.data.progress().snapshot()) {
+            (Entry::Continue, Some(snapshot)) => {
+                debug!("Continue campaign from level {}", snapshot.level);
+                InGameState::resume(generator, snapshot.clone())
+            }
+            (Entry::Campaign, _) => {
+                debug!("Start a new campaign from level {}", self.level);
+                InGameState::new(generator, GameMode::Campaign, self.difficulty, self.level)
+            }
+            (Entry::Continue, None) => {
+                debug!("No campaign to continue, start a new one");
+                InGameState::new(generator, GameMode::Campaign, self.difficulty, 0)
+            }
+            (Entry::Classic | Entry::Options, _) => {
+                debug!("Start a new game");
+                InGameState::new(generator, GameMode::Classic, self.difficulty, 0)
+            }
+        };
+        Transition::Push(State::InGame(state))
+    }
+
+    fn change_row_value(&mut self, forward: bool) {
+        match self.row {
+            Row::Entry => {
+                let entries = self.entries();
+                let count = entries.iter().flatten().count();
+                let index = entries.iter().flatten().position(|e| *e == self.entry);
+                let index = index.unwrap_or(0);
+                let index = if forward {
+                    (index + 1) % count
+                } else {
+                    (index + count - 1) % count
+                };
+                self.entry = entries
+                    .iter()
+                    .flatten()
+                    .nth(index)
+                    .copied()
+                    .unwrap_or(Entry::Classic);
+            }
+            Row::Difficulty => {
+                self.difficulty = if forward {
+                    self.difficulty.next()
+                } else {
+                    self.difficulty.previous()
+                };
+            }
+            Row::Level => {
+                let count = self.data.progress().highest_level() + 1;
+                self.level = if forward {
+                    (self.level + 1) % count
+                } else {
+                    (self.level + count - 1) % count
+                };
+            }
+        }
+    }
+
+    /// Return the entries currently available
+    fn entries(&self) -> [Option<Entry>; 4] {
+        let can_continue = self.data.progress().snapshot().is_some();
+        [
+            can_continue.then_some(Entry::Continue),
+            Some(Entry::Campaign),
+            Some(Entry::Classic),
+            Some(Entry::Options),
+        ]
+    }
+
+    /// Return the rows currently visible
+    fn rows(&self) -> [Option<Row>; 3] {
+        [
+            Some(Row::Entry),
+            (self.entry != Entry::Continue && self.entry != Entry::Options)
+                .then_some(Row::Difficulty),
+            (self.entry == Entry::Campaign).then_some(Row::Level),
+        ]
+    }
+
+    fn leaderboard(&self) -> Leaderboard {
+        match (self.entry, self.data.progress().snapshot()) {
+            (Entry::Continue, Some(snapshot)) => {
+                Leaderboard::new(GameMode::Campaign, snapshot.difficulty)
+            }
+            (Entry::Campaign, _) => Leaderboard::new(GameMode::Campaign, self.difficulty),
+            _ => Leaderboard::new(GameMode::Classic, self.difficulty),
+        }
+    }
+
     fn draw_logo(&self) {
         let x = 0;
         let y = 5;
 
-        set_drawing_colors(0x03);
+        Role::Muted.set();
         LOGO_SPRITE.blit(x + 1, y + 1);
         LOGO_SPRITE.blit(x + 2, y + 2);
 
-        set_drawing_colors(0x02);
+        Role::Accent.set();
         LOGO_SPRITE.blit(x, y);
     }
 
     fn draw_highscores(&self) {
-        if self.highscores.is_empty() {
+        let highscores = self.data.highscores().get(self.leaderboard());
+        if highscores.is_empty() {
             return;
         }
 
         let text = "HIGH SCORES";
-        let y = 36;
+        let y = 28;
 
-        set_drawing_colors(3);
+        Role::Muted.set();
         draw_centered_text(text, y);
 
         let mut buffer = [0; 128];
@@ -107,54 +273,79 @@ impl MainMenuState {
         let x = ((SCREEN_SIZE - 14 * 4) / 2) as i32;
         let y = y + 10;
 
-        for (i, highscore) in self.highscores.iter().enumerate() {
-            set_drawing_colors(0x02);
+        for (i, highscore) in highscores.iter().enumerate() {
+            Role::Accent.set();
             draw_4x4_text(highscore.name(), x, y + i as i32 * 5);
 
             let text = format_i32_padded(&mut buffer, highscore.score() as i32, 5, ' ');
-            set_drawing_colors(0x04);
+            Role::Ink.set();
             draw_4x4_text(text, x + 4 * 4, y + i as i32 * 5);
         }
     }
 
+    fn draw_rows(&self) {
+        let mut buffer = [0; 4];
+        let y = 68;
+
+        for (i, row) in self.rows().iter().flatten().enumerate() {
+            let y = y + 9 * i as i32;
+            let (label, value) = match row {
+                Row::Entry => ("MODE", self.entry.name()),
+                Row::Difficulty => ("DIFFICULTY", self.difficulty.name()),
+                Row::Level => ("LEVEL", format_i32(&mut buffer, self.level as i32 + 1)),
+            };
+
+            if *row == self.row {
+                Role::Accent.set();
+                draw_5x8_text("<", 84, y);
+                draw_5x8_text(">", 136, y);
+            } else {
+                Role::Muted.set();
+            }
+            draw_5x8_text(label, 20, y);
+            draw_5x8_text(value, 92, y);
+        }
+    }
+
+    fn draw_high_scores_hint(&self) {
+        Role::Muted.set();
+        draw_centered_4x4_text("PRESS X FOR ALL HIGH SCORES", 118);
+    }
+
     fn draw_press_button_to_start(&self) {
-        let text = "Press    to start";
+        let text = "Press     to start";
         let height = 8;
-        let width = text.len() as u32 * 5;
+        let width = text_width(text);
         let x = (SCREEN_SIZE - width) as i32 / 2;
-        let y = 100;
+        let y = 102;
 
-        if Ticker.within_second() < 30 {
-            set_drawing_colors(0x02);
+        // The text blinks along with the button being pressed
+        let frame = animations::BUTTON.animation().frame();
+        if frame == 0 {
+            Role::Accent.set();
         } else {
-            set_drawing_colors(0x03);
+            Role::Muted.set();
         }
         draw_5x8_text(text, x, y);
 
         self.draw_margin(x - 2, y - 6, width + 2 + 1, height + 2 + 3 + 4);
 
-        let src_x;
-        let offset;
-        if Ticker.within_second() < 30 {
-            src_x = 0;
-            offset = 0;
-        } else {
-            src_x = 16;
-            offset = 1;
-        };
+        let button_width = BUTTON_SPRITE.width() / 2;
+        let src_x = frame * button_width;
+        let offset = frame as i32;
 
-        let button_x = x + 5 * 5 + 3;
+        let button_x = x + text_width("Press") as i32 + 3;
         let button_y = y - 5;
-        set_drawing_colors(0x1230);
+        Role::Sprite.set();
         BUTTON_SPRITE
-            .clip(src_x, 0, BUTTON_SPRITE.width() / 2, BUTTON_SPRITE.height())
+            .clip(src_x, 0, button_width, BUTTON_SPRITE.height())
             .blit(button_x, button_y);
-        set_drawing_colors(0x03);
-        draw_5x8_text("z", button_x + 5, button_y + 5 + offset);
+        Role::Muted.set();
+        draw_5x8_text("z", button_x + 6, button_y + 5 + offset);
     }
 
     fn draw_margin(&self, x: i32, y: i32, width: u32, height: u32) {
-        set_drawing_colors(0x02);
+        Role::Accent.set();
 
         for i in 0..(width / 4) {
             let xi = x + 4 * i as i32 + self.step as i32;
@@ -179,20 +370,20 @@ impl MainMenuState {
         let width = SCREEN_SIZE - x as u32 * 2;
         let height = line_height as u32 * 3;
 
-        set_drawing_colors(0x33);
+        Role::MutedFill.set();
         draw_rect(x, y, width + 4, height + 1);
 
-        set_drawing_colors(0x22);
+        Role::AccentFill.set();
         draw_rect(x - 2, y - 2, width + 4, height + 1);
 
-        set_drawing_colors(0x03);
+        Role::Muted.set();
 
         let text = concat!("Version ", env!("CARGO_PKG_VERSION"));
         draw_5x8_text(&text, x, y);
 
         let text = "Developed by ";
         draw_5x8_text(&text, x, y + line_height);
-        let offset = text.len() as i32 * 5;
+        let offset = text_width(text) as i32 + 1;
 
         let text = env!("CARGO_PKG_AUTHORS")
             .split_once('<')
@@ -202,6 +393,57 @@ impl MainMenuState {
         draw_5x8_text(&text, x + offset, y + line_height);
 
         let text = "for WASM-4 JAM (January 2022)";
-        draw_5x8_text(&text, x, y + line_height * 2);
+        draw_wrapped_5x8_text(text, x, y + line_height * 2, width, Alignment::Left);
+    }
+}
+
+impl GameState for MainMenuState {
+    fn name(&self) -> &'static str {
+        "main_menu"
+    }
+
+    fn music(&self) -> Option<Music> {
+        Some(Music::Menu)
+    }
+
+    /// Fade in whenever the main menu is shown again
+    fn enter_effect(&self) -> Effect {
+        Effect::Fade(30)
+    }
+
+    /// Load the saved data and select the default entry
+    fn on_enter(&mut self) {
+        self.data = SaveData::load();
+        self.entry = if self.data.progress().snapshot().is_some() {
+            Entry::Continue
+        } else {
+            Entry::Classic
+        };
+        self.cooldown = MAX_COOLDOWN;
+    }
+
+    /// Reload the saved data, which a game might have changed
+    fn on_resume(&mut self) {
+        self.data = SaveData::load();
+        if self.entry == Entry::Continue && self.data.progress().snapshot().is_none() {
+            self.entry = Entry::Classic;
+            self.row = Row::Entry;
+        }
+        self.cooldown = MAX_COOLDOWN;
+    }
+
+    fn draw(&self, _gamepad: Option<&GamePad>) {
+        self.draw_logo();
+        self.draw_highscores();
+        self.draw_rows();
+        self.draw_press_button_to_start();
+        self.draw_high_scores_hint();
+        self.draw_credits();
+    }
+
+    fn update(&mut self, gamepad: &GamePad, audio_queue: &mut AudioQueue) -> Transition {
+        self.update_cooldowns();
+        self.update_step();
+        self.handle_input(gamepad, audio_queue)
     }
 }
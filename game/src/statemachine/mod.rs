@@ -6,6 +6,9 @@
 
 //! State machine data types and functions
 
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
 use tinyvec::array_vec;
 use tinyvec::ArrayVec;
 
@@ -13,6 +16,23 @@ use wasm4fun_input::GamePad;
 use wasm4fun_log::debug;
 
 use crate::audio::AudioQueue;
+use crate::music::Music;
+
+#[cfg(feature = "debug")]
+use crate::debugoverlay::Cheat;
+
+mod effect;
+pub use effect::Effect;
+use effect::{ActiveEffect, Direction};
+
+mod error;
+use error::ErrorState;
+
+mod gameover;
+use gameover::GameOverState;
+
+mod highscores;
+use highscores::HighScoresState;
 
 mod ingame;
 use ingame::InGameState;
@@ -20,12 +40,26 @@ use ingame::InGameState;
 mod initial;
 use initial::InitialState;
 
+mod levelcomplete;
+use levelcomplete::LevelCompleteState;
+
+mod levelintro;
+use levelintro::{format_round_title, LevelIntroState};
+
 mod mainmenu;
 use mainmenu::MainMenuState;
 
+mod options;
+use options::OptionsState;
+
 mod savescore;
 use savescore::SaveScoreState;
 
+#[cfg(feature = "debug")]
+mod sfxeditor;
+#[cfg(feature = "debug")]
+use sfxeditor::SfxEditorState;
+
 mod splashscreen;
 use splashscreen::SplashScreenState;
 
@@ -43,20 +77,42 @@ const MAX_STATES: usize = 5;
 /// * The current state can switch to a different state;
 /// * A new state can be pushed to the stack;
 /// * The current state can be popped from the stack.
+///
+/// Transitions that would overflow or empty the stack are not applied, and the
+/// stack is replaced with an error state instead.
+///
+/// When feature `storage` is enabled, the whole machine, including the data of
+/// every state in the stack, can be serialized.
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
 pub struct Machine {
     states_stack: ArrayVec<[State; MAX_STATES]>,
+    effect: Option<ActiveEffect>,
+    pending: Option<Transition>,
 }
 
 impl Machine {
     /// Create a new stack machine
     pub fn new() -> Self {
         let states_stack = array_vec!([State; MAX_STATES] => State::Initial(InitialState::new()));
-        Machine { states_stack }
+        Machine {
+            states_stack,
+            effect: None,
+            pending: None,
+        }
     }
 
-    /// Draw all states in the stack
+    /// Draw the states in the stack, and the effect being played on top
+    ///
+    /// States below the topmost opaque state are hidden, so they are not
+    /// drawn at all.
     pub fn draw(&self, gamepad: &GamePad) {
-        for (i, state) in self.states_stack.iter().enumerate() {
+        let first = self
+            .states_stack
+            .iter()
+            .rposition(GameState::is_opaque)
+            .unwrap_or(0);
+
+        for (i, state) in self.states_stack.iter().enumerate().skip(first) {
             let gamepad = if i == self.states_stack.len() - 1 {
                 Some(gamepad)
             } else {
@@ -64,6 +120,10 @@ impl Machine {
             };
             state.draw(gamepad);
         }
+
+        if let Some(effect) = &self.effect {
+            effect.draw();
+        }
     }
 
     /// Update the top state on the stack
@@ -71,28 +131,110 @@ impl Machine {
     /// The update returns a transition, which might change the content of the
     /// stack.
     ///
-    /// The top state is always popped from the stack.
-    /// If the current state wants to remain on the stack, it must return a
-    /// [`Transition::Replace`] transition containing itself.
+    /// When the top state leaves the stack, its exit effect is played before
+    /// the transition is applied.
+    /// When a new state reaches the top of the stack, its enter effect is
+    /// played afterwards.
+    /// States are not updated while an effect is playing.
     pub fn update(&mut self, gamepad: &GamePad, audio_queue: &mut AudioQueue) {
-        let stack_size = self.states_stack.len();
+        audio_queue.play_music(self.states_stack.last().and_then(GameState::music));
+
+        if let Some(effect) = self.effect.as_mut() {
+            effect.update();
+            if effect.is_done() {
+                effect.finish();
+                let direction = effect.direction();
+                self.effect = None;
+
+                if direction == Direction::Out {
+                    if let Some(transition) = self.pending.take() {
+                        self.apply_or_report(transition);
+                    }
+                }
+            }
+            return;
+        }
 
-        let state: &mut State = self
-            .states_stack
-            .last_mut()
-            .expect("Empty state machine!!!");
+        let state: &mut State = match self.states_stack.last_mut() {
+            Some(state) => state,
+            None => {
+                self.report(MachineError::Empty);
+                return;
+            }
+        };
+        let exit_effect = state.exit_effect();
         let transition: Transition = state.update(gamepad, audio_queue);
 
+        match transition {
+            Transition::Noop => {}
+            Transition::Replace(_) | Transition::Pop | Transition::PopN(_)
+                if exit_effect != Effect::None =>
+            {
+                debug!("Playing exit effect of state {}", state.name());
+                self.effect = Some(ActiveEffect::new(exit_effect, Direction::Out));
+                self.pending = Some(transition);
+            }
+            transition => self.apply_or_report(transition),
+        }
+    }
+
+    /// Apply a transition, or switch to the error state if it is not valid
+    fn apply_or_report(&mut self, transition: Transition) {
+        match self.apply(transition) {
+            Ok(()) => self.start_enter_effect(),
+            Err(error) => self.report(error),
+        }
+    }
+
+    /// Replace the whole stack with a state reporting an error
+    fn report(&mut self, error: MachineError) {
+        debug!("State machine error: {}", error.message());
+
+        if let Some(effect) = self.effect.take() {
+            effect.finish();
+        }
+        self.pending = None;
+
+        while let Some(mut state) = self.states_stack.pop() {
+            state.on_exit();
+        }
+        self.push(State::Error(ErrorState::new(error)));
+    }
+
+    /// Start the enter effect of the top state on the stack, if any
+    fn start_enter_effect(&mut self) {
+        if let Some(state) = self.states_stack.last() {
+            let enter_effect = state.enter_effect();
+            if enter_effect != Effect::None {
+                debug!("Playing enter effect of state {}", state.name());
+                self.effect = Some(ActiveEffect::new(enter_effect, Direction::In));
+            }
+        }
+    }
+
+    /// Apply a transition to the stack
+    ///
+    /// The lifecycle hooks of the states are called as they are pushed,
+    /// popped, covered or uncovered.
+    ///
+    /// The stack is left untouched if the transition would overflow or empty
+    /// it.
+    fn apply(&mut self, transition: Transition) -> Result<(), MachineError> {
+        self.check(&transition)?;
+
+        let stack_size = self.states_stack.len();
+
         match transition {
             Transition::Replace(new_state) => {
                 debug!("There are {} states in the stack", stack_size);
+                let mut state = self.states_stack.pop().expect("Empty state machine!!!");
                 debug!(
                     "Replacing state {} with state {}",
                     state.name(),
                     new_state.name()
                 );
-                self.states_stack.pop();
-                self.states_stack.push(new_state);
+                state.on_exit();
+                self.push(new_state);
                 for state in &self.states_stack {
                     debug!("  - {}", state.name());
                 }
@@ -100,7 +242,8 @@ impl Machine {
             Transition::Push(new_state) => {
                 debug!("There are {} states in the stack", stack_size);
                 debug!("Pushing new state {} to stack", new_state.name());
-                self.states_stack.push(new_state);
+                self.pause_top();
+                self.push(new_state);
                 for state in &self.states_stack {
                     debug!("  - {}", state.name());
                 }
@@ -109,7 +252,8 @@ impl Machine {
                 debug!("There are {} states in the stack", stack_size);
                 for new_state in new_states {
                     debug!("Pushing new state {} to stack", new_state.name());
-                    self.states_stack.push(new_state);
+                    self.pause_top();
+                    self.push(new_state);
                 }
                 for state in &self.states_stack {
                     debug!("  - {}", state.name());
@@ -117,8 +261,10 @@ impl Machine {
             }
             Transition::Pop => {
                 debug!("There are {} states in the stack", stack_size);
+                let mut state = self.states_stack.pop().expect("Empty state machine!!!");
                 debug!("Popping state {} from stack", state.name());
-                self.states_stack.pop();
+                state.on_exit();
+                self.resume_top();
                 debug!(
                     "New top is {}",
                     self.states_stack.iter().last().unwrap().name()
@@ -131,13 +277,11 @@ impl Machine {
                 debug!("There are {} states in the stack", stack_size);
                 debug!("Popping {} states from stack", n);
                 for _ in 0..n {
-                    let state: &mut State = self
-                        .states_stack
-                        .last_mut()
-                        .expect("Empty state machine!!!");
+                    let mut state = self.states_stack.pop().expect("Empty state machine!!!");
                     debug!("Popping state {} from stack", state.name());
-                    self.states_stack.pop();
+                    state.on_exit();
                 }
+                self.resume_top();
                 debug!(
                     "New top is {}",
                     self.states_stack.iter().last().unwrap().name()
@@ -148,10 +292,102 @@ impl Machine {
             }
             Transition::Noop => {}
         }
+
+        Ok(())
+    }
+
+    /// Check whether a transition can be applied to the stack
+    fn check(&self, transition: &Transition) -> Result<(), MachineError> {
+        let stack_size = self.states_stack.len();
+
+        match transition {
+            Transition::Replace(_) if stack_size == 0 => Err(MachineError::Empty),
+            Transition::Push(_) if stack_size >= MAX_STATES => Err(MachineError::Overflow),
+            Transition::PushN(new_states) if stack_size + new_states.len() > MAX_STATES => {
+                Err(MachineError::Overflow)
+            }
+            Transition::Pop if stack_size <= 1 => Err(MachineError::Underflow),
+            Transition::PopN(n) if *n >= stack_size => Err(MachineError::Underflow),
+            _ => Ok(()),
+        }
+    }
+
+    /// Push a state to the stack and let it know it was entered
+    fn push(&mut self, mut state: State) {
+        state.on_enter();
+        self.states_stack.push(state);
+    }
+
+    /// Let the top state know it is being covered
+    fn pause_top(&mut self) {
+        if let Some(state) = self.states_stack.last_mut() {
+            state.on_pause();
+        }
+    }
+
+    /// Let the top state know it is being uncovered
+    fn resume_top(&mut self) {
+        if let Some(state) = self.states_stack.last_mut() {
+            state.on_resume();
+        }
+    }
+}
+
+#[cfg(feature = "debug")]
+impl Machine {
+    /// Return the names of the states in the stack, from bottom to top
+    pub fn state_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.states_stack.iter().map(GameState::name)
+    }
+
+    /// Draw the debug information of the top state on the stack
+    pub fn draw_debug(&self) {
+        if let Some(state) = self.states_stack.last() {
+            state.draw_debug();
+        }
+    }
+
+    /// Apply a cheat to the top state on the stack
+    ///
+    /// Cheats that open a debug state are handled by the machine itself.
+    pub fn cheat(&mut self, cheat: Cheat) {
+        match cheat {
+            Cheat::OpenSfxEditor => {
+                if !matches!(self.states_stack.last(), Some(State::SfxEditor(_))) {
+                    let editor = State::SfxEditor(SfxEditorState::new());
+                    self.apply_or_report(Transition::Push(editor));
+                }
+            }
+            _ => {
+                if let Some(state) = self.states_stack.last_mut() {
+                    state.cheat(cheat);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "snapshot")]
+impl Machine {
+    /// Serialize the state machine to a buffer
+    ///
+    /// Return the portion of the buffer that was used.
+    pub fn save_snapshot<'a>(&self, buffer: &'a mut [u8]) -> Result<&'a mut [u8], postcard::Error> {
+        postcard::to_slice(self, buffer)
+    }
+
+    /// Deserialize a state machine from a buffer
+    pub fn load_snapshot(bytes: &[u8]) -> Result<Self, postcard::Error> {
+        postcard::from_bytes(bytes)
     }
 }
 
 /// A state transition
+///
+/// States cannot be boxed without an allocator, so transitions carry them by
+/// value.
+#[allow(clippy::large_enum_variant)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
 pub enum Transition {
     /// The current state is replaced with a new state
     #[allow(unused)]
@@ -178,19 +414,140 @@ pub enum Transition {
     Noop,
 }
 
+/// An error in the state machine
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub enum MachineError {
+    /// A transition would push more states than the stack can hold
+    Overflow,
+
+    /// A transition would pop all states from the stack
+    Underflow,
+
+    /// There is no state in the stack
+    Empty,
+
+    /// The invalid placeholder state reached the top of the stack
+    InvalidState,
+}
+
+impl MachineError {
+    /// Return a description of the error
+    pub fn message(&self) -> &'static str {
+        match self {
+            MachineError::Overflow => "TOO MANY STATES",
+            MachineError::Underflow => "TOO FEW STATES",
+            MachineError::Empty => "NO STATE",
+            MachineError::InvalidState => "INVALID STATE",
+        }
+    }
+}
+
 /// A game state
 ///
 /// Each state maintains its own state data, which is also responsible for
 /// drawing and updating itself.
-#[allow(unused)]
-pub enum State {
-    /// An invalid state
-    Invalid,
+///
+/// The lifecycle hooks are called by the [`Machine`] as the state moves
+/// through the stack, and do nothing by default.
+pub trait GameState {
+    /// Return the name of the state
+    fn name(&self) -> &'static str;
+
+    /// Return the music played while the state is on top of the stack
+    fn music(&self) -> Option<Music> {
+        None
+    }
+
+    /// Return the effect played when the state reaches the top of the stack
+    fn enter_effect(&self) -> Effect {
+        Effect::None
+    }
+
+    /// Return the effect played when the state leaves the stack
+    fn exit_effect(&self) -> Effect {
+        Effect::None
+    }
+
+    /// Return whether the state covers the whole screen
+    ///
+    /// States below an opaque state are not drawn, while overlays are drawn
+    /// on top of the states below them.
+    fn is_opaque(&self) -> bool {
+        true
+    }
+
+    /// Called when the state is pushed to the stack
+    fn on_enter(&mut self) {}
+
+    /// Called when the state is popped from the stack
+    fn on_exit(&mut self) {}
+
+    /// Called when another state is pushed on top of the state
+    fn on_pause(&mut self) {}
+
+    /// Called when the state on top of the state is popped
+    fn on_resume(&mut self) {}
+
+    /// Draw the state
+    fn draw(&self, gamepad: Option<&GamePad>);
+
+    /// Update the state
+    fn update(&mut self, gamepad: &GamePad, audio_queue: &mut AudioQueue) -> Transition;
+
+    /// Draw debug information on top of the state
+    #[cfg(feature = "debug")]
+    fn draw_debug(&self) {}
+
+    /// Apply a cheat to the state
+    #[cfg(feature = "debug")]
+    fn cheat(&mut self, _cheat: Cheat) {}
+}
+
+/// Define the [`State`] enum wrapping every type of game state
+///
+/// States cannot be boxed without an allocator, so the stack stores them in
+/// an enum, and this macro generates the dispatch to the [`GameState`] trait.
+/// Adding a new state only requires a new line here.
+/// A state can be restricted to some features with a `#[cfg(...)]` attribute
+/// after its documentation.
+macro_rules! states {
+    ($($(#[doc = $doc:literal])* $(#[cfg($cfg:meta)])? $variant:ident($state:ty),)*) => {
+        /// A game state stored in the stack
+        #[allow(unused, clippy::large_enum_variant)]
+        #[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+        pub enum State {
+            /// An invalid state, used as placeholder for empty stack slots
+            Invalid,
+
+            $($(#[doc = $doc])* $(#[cfg($cfg)])? $variant($state),)*
+        }
+
+        impl State {
+            /// Return the wrapped state, if any
+            fn as_game_state(&self) -> Option<&dyn GameState> {
+                match self {
+                    State::Invalid => None,
+                    $($(#[cfg($cfg)])? State::$variant(state) => Some(state),)*
+                }
+            }
+
+            /// Return the wrapped state as mutable, if any
+            fn as_game_state_mut(&mut self) -> Option<&mut dyn GameState> {
+                match self {
+                    State::Invalid => None,
+                    $($(#[cfg($cfg)])? State::$variant(state) => Some(state),)*
+                }
+            }
+        }
+    };
+}
 
+states! {
     /// The initial state, created at the state machine initialization
     Initial(InitialState),
 
-    // The splash screen state
+    /// The splash screen state
     SplashScreen(SplashScreenState),
 
     /// The main menu state
@@ -199,54 +556,106 @@ pub enum State {
     /// The in-game state
     InGame(InGameState),
 
+    /// The level intro state
+    LevelIntro(LevelIntroState),
+
+    /// The level complete state
+    LevelComplete(LevelCompleteState),
+
+    /// The game over state
+    GameOver(GameOverState),
+
     /// The high-score state
     SaveScore(SaveScoreState),
 
+    /// The high-scores browser state
+    HighScores(HighScoresState),
+
+    /// The options state
+    Options(OptionsState),
+
     /// The test-intersections state
     TestIntersections(TestIntersectionsState),
+
+    /// The state machine error state
+    Error(ErrorState),
+
+    /// The sound effects editor state
+    #[cfg(feature = "debug")]
+    SfxEditor(SfxEditorState),
 }
 
-impl State {
-    /// Return the name of the state
-    pub fn name(&self) -> &'static str {
-        match self {
-            State::Invalid => "invalid",
-            State::Initial(_) => "initial",
-            State::SplashScreen(_) => "splashscreen",
-            State::MainMenu(_) => "main_menu",
-            State::InGame(_) => "in_game",
-            State::SaveScore(_) => "save_score",
-            State::TestIntersections(_) => "test_intersections",
+impl GameState for State {
+    fn name(&self) -> &'static str {
+        self.as_game_state().map_or("invalid", GameState::name)
+    }
+
+    fn music(&self) -> Option<Music> {
+        self.as_game_state().and_then(GameState::music)
+    }
+
+    fn enter_effect(&self) -> Effect {
+        self.as_game_state()
+            .map_or(Effect::None, GameState::enter_effect)
+    }
+
+    fn exit_effect(&self) -> Effect {
+        self.as_game_state()
+            .map_or(Effect::None, GameState::exit_effect)
+    }
+
+    fn is_opaque(&self) -> bool {
+        self.as_game_state().is_some_and(GameState::is_opaque)
+    }
+
+    fn on_enter(&mut self) {
+        if let Some(state) = self.as_game_state_mut() {
+            state.on_enter();
         }
     }
 
-    /// Draw the current state
-    ///
-    /// This function delegates the drawing to the state data.
-    pub fn draw(&self, gamepad: Option<&GamePad>) {
-        match self {
-            State::Invalid => panic!(),
-            State::Initial(s) => s.draw(gamepad),
-            State::SplashScreen(s) => s.draw(gamepad),
-            State::MainMenu(s) => s.draw(gamepad),
-            State::InGame(s) => s.draw(gamepad),
-            State::SaveScore(s) => s.draw(gamepad),
-            State::TestIntersections(s) => s.draw(gamepad),
+    fn on_exit(&mut self) {
+        if let Some(state) = self.as_game_state_mut() {
+            state.on_exit();
         }
     }
 
-    /// Update the current state
-    ///
-    /// This function delegates the update to the state data.
-    pub fn update(&mut self, gamepad: &GamePad, audio_queue: &mut AudioQueue) -> Transition {
-        match self {
-            State::Invalid => panic!(),
-            State::Initial(state) => state.update(gamepad, audio_queue),
-            State::SplashScreen(state) => state.update(gamepad, audio_queue),
-            State::MainMenu(state) => state.update(gamepad, audio_queue),
-            State::InGame(state) => state.update(gamepad, audio_queue),
-            State::SaveScore(state) => state.update(gamepad, audio_queue),
-            State::TestIntersections(state) => state.update(gamepad, audio_queue),
+    fn on_pause(&mut self) {
+        if let Some(state) = self.as_game_state_mut() {
+            state.on_pause();
+        }
+    }
+
+    fn on_resume(&mut self) {
+        if let Some(state) = self.as_game_state_mut() {
+            state.on_resume();
+        }
+    }
+
+    fn draw(&self, gamepad: Option<&GamePad>) {
+        if let Some(state) = self.as_game_state() {
+            state.draw(gamepad);
+        }
+    }
+
+    fn update(&mut self, gamepad: &GamePad, audio_queue: &mut AudioQueue) -> Transition {
+        match self.as_game_state_mut() {
+            Some(state) => state.update(gamepad, audio_queue),
+            None => Transition::Replace(State::Error(ErrorState::new(MachineError::InvalidState))),
+        }
+    }
+
+    #[cfg(feature = "debug")]
+    fn draw_debug(&self) {
+        if let Some(state) = self.as_game_state() {
+            state.draw_debug();
+        }
+    }
+
+    #[cfg(feature = "debug")]
+    fn cheat(&mut self, cheat: Cheat) {
+        if let Some(state) = self.as_game_state_mut() {
+            state.cheat(cheat);
         }
     }
 }
@@ -256,3 +665,142 @@ impl Default for State {
         State::Invalid
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overflow_is_recoverable() {
+        let mut machine = Machine::new();
+        for _ in 1..MAX_STATES {
+            let transition = Transition::Push(State::Initial(InitialState::new()));
+            assert_eq!(machine.apply(transition), Ok(()));
+        }
+
+        let transition = Transition::Push(State::Initial(InitialState::new()));
+        assert_eq!(machine.apply(transition), Err(MachineError::Overflow));
+        assert_eq!(machine.states_stack.len(), MAX_STATES);
+    }
+
+    #[test]
+    fn test_underflow_is_recoverable() {
+        let mut machine = Machine::new();
+        assert_eq!(machine.apply(Transition::Pop), Err(MachineError::Underflow));
+        assert_eq!(
+            machine.apply(Transition::PopN(3)),
+            Err(MachineError::Underflow)
+        );
+        assert_eq!(machine.states_stack.len(), 1);
+    }
+
+    #[test]
+    fn test_error_replaces_stack() {
+        let mut machine = Machine::new();
+        machine.report(MachineError::Empty);
+
+        let names = machine.states_stack.iter().map(State::name);
+        assert!(names.eq(["error"]));
+    }
+
+    #[cfg(feature = "snapshot")]
+    #[test]
+    fn test_snapshot_roundtrip() {
+        use crate::game::{Difficulty, GameMode};
+        use crate::random::Generator;
+
+        let mut machine = Machine::new();
+        let generator = Generator::new(42);
+        let state = InGameState::new(generator, GameMode::Classic, Difficulty::Hard, 0);
+        machine.states_stack.push(State::InGame(state));
+
+        let mut buffer = [0; 2048];
+        let bytes = machine.save_snapshot(&mut buffer).unwrap();
+
+        let restored = Machine::load_snapshot(bytes).unwrap();
+        let names = restored.states_stack.iter().map(State::name);
+        assert!(names.eq(["initial", "in_game"]));
+
+        let mut restored_buffer = [0; 2048];
+        let restored_bytes = restored.save_snapshot(&mut restored_buffer).unwrap();
+        assert_eq!(restored_bytes, bytes);
+    }
+
+    #[cfg(feature = "snapshot")]
+    #[test]
+    fn test_largest_snapshot_fits_buffer() {
+        use crate::game::{
+            Ball, Bar, CampaignSnapshot, Difficulty, GameMode, HighScore, Leaderboard, Level,
+            Playfield, SaveData, Stats,
+        };
+        use crate::random::Generator;
+        use crate::snapshot::SNAPSHOT_SIZE;
+
+        let mut data = SaveData::default();
+        let mut leaderboard = Leaderboard::default();
+        loop {
+            for _ in 0..10 {
+                let highscore = HighScore::new(['W', 'W', 'W'], u32::MAX);
+                data.highscores_mut().get_mut(leaderboard).add(highscore);
+            }
+            leaderboard = leaderboard.next();
+            if leaderboard == Leaderboard::default() {
+                break;
+            }
+        }
+        data.set_last_name(['W', 'W', 'W']);
+        data.progress_mut().unlock(u8::MAX);
+        data.progress_mut().set_snapshot(Some(CampaignSnapshot {
+            difficulty: Difficulty::Hard,
+            level: u8::MAX,
+            score: u32::MAX,
+            lives: u8::MAX,
+            bar: Bar::new(&Playfield::DEFAULT),
+            ball: Ball::new((0.6, -0.8), 100.0, &Playfield::DEFAULT),
+        }));
+
+        let densest_level = (0..Level::count())
+            .max_by_key(|&level| Level::get(level).bricks().len())
+            .unwrap();
+        let generator = Generator::new(42);
+        let mut in_game = InGameState::new(
+            generator,
+            GameMode::Campaign,
+            Difficulty::Hard,
+            densest_level,
+        );
+        in_game.fill();
+
+        let mut machine = Machine::new();
+        machine.states_stack.clear();
+        machine
+            .states_stack
+            .push(State::MainMenu(MainMenuState::with_data(data)));
+        machine.states_stack.push(State::InGame(in_game));
+        machine
+            .states_stack
+            .push(State::SfxEditor(SfxEditorState::new()));
+        machine
+            .states_stack
+            .push(State::Options(OptionsState::new()));
+        machine
+            .states_stack
+            .push(State::LevelComplete(LevelCompleteState::new(
+                u8::MAX,
+                Stats::default(),
+                u32::MAX,
+            )));
+        assert_eq!(machine.states_stack.len(), MAX_STATES);
+
+        let mut buffer = [0; 2 * SNAPSHOT_SIZE];
+        let bytes = machine.save_snapshot(&mut buffer).unwrap();
+        assert!(
+            bytes.len() <= SNAPSHOT_SIZE,
+            "snapshot is {} bytes",
+            bytes.len()
+        );
+
+        let restored = Machine::load_snapshot(bytes).unwrap();
+        assert_eq!(restored.states_stack.len(), MAX_STATES);
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use wasm4fun_graphics::{draw_centered_4x4_text, draw_rect, SCREEN_SIZE};
+use wasm4fun_input::GamePad;
+
+use crate::audio::{AudioQueue, Sfx};
+use crate::font::{draw_5x8_text, draw_centered_5x8_text};
+use crate::game::{SaveData, Settings};
+use crate::graphics::dim_screen;
+use crate::music::Music;
+use crate::theme::Role;
+
+use super::{GameState, Transition};
+
+const MAX_COOLDOWN: u8 = 10;
+
+/// A row in the options panel
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+enum Row {
+    Theme,
+    BallTrail,
+    ScreenShake,
+    HitStop,
+}
+
+/// All rows, in the order they are shown
+const ROWS: [Row; 4] = [Row::Theme, Row::BallTrail, Row::ScreenShake, Row::HitStop];
+
+/// A state for changing the player settings
+///
+/// Changes are applied immediately, and saved when leaving the state.
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct OptionsState {
+    settings: Settings,
+    row: usize,
+    cooldown: u8,
+}
+
+impl OptionsState {
+    pub fn new() -> Self {
+        Self {
+            settings: Settings::default(),
+            row: 0,
+            cooldown: MAX_COOLDOWN,
+        }
+    }
+
+    fn change_row_value(&mut self, forward: bool) {
+        match ROWS[self.row] {
+            Row::Theme => {
+                let theme = self.settings.theme();
+                let theme = if forward {
+                    theme.next()
+                } else {
+                    theme.previous()
+                };
+                self.settings.set_theme(theme);
+                theme.apply();
+            }
+            Row::BallTrail => {
+                self.settings.set_ball_trail(!self.settings.ball_trail());
+            }
+            Row::ScreenShake => {
+                self.settings
+                    .set_screen_shake(!self.settings.screen_shake());
+            }
+            Row::HitStop => {
+                self.settings.set_hit_stop(!self.settings.hit_stop());
+            }
+        }
+    }
+
+    fn save(&self) {
+        let mut data = SaveData::load();
+        *data.settings_mut() = self.settings;
+        data.save();
+    }
+}
+
+impl GameState for OptionsState {
+    fn name(&self) -> &'static str {
+        "options"
+    }
+
+    fn music(&self) -> Option<Music> {
+        Some(Music::Menu)
+    }
+
+    /// Load the current settings
+    fn on_enter(&mut self) {
+        self.settings = *SaveData::load().settings();
+        self.cooldown = MAX_COOLDOWN;
+    }
+
+    /// The options are drawn as a panel over the dimmed main menu
+    fn is_opaque(&self) -> bool {
+        false
+    }
+
+    fn draw(&self, _gamepad: Option<&GamePad>) {
+        dim_screen();
+
+        Role::Panel.set();
+        draw_rect(8, 8, SCREEN_SIZE - 16, SCREEN_SIZE - 16);
+
+        Role::Accent.set();
+        draw_centered_5x8_text("OPTIONS", 16);
+
+        let y = 40;
+        for (i, row) in ROWS.iter().enumerate() {
+            let y = y + 12 * i as i32;
+            let (label, value) = match row {
+                Row::Theme => ("THEME", self.settings.theme().name()),
+                Row::BallTrail => ("TRAIL", on_off(self.settings.ball_trail())),
+                Row::ScreenShake => ("SHAKE", on_off(self.settings.screen_shake())),
+                Row::HitStop => ("HIT STOP", on_off(self.settings.hit_stop())),
+            };
+
+            if i == self.row {
+                Role::Accent.set();
+                draw_5x8_text("<", 66, y);
+                draw_5x8_text(">", SCREEN_SIZE as i32 - 16 - 5, y);
+            } else {
+                Role::Muted.set();
+            }
+            draw_5x8_text(label, 16, y);
+            draw_5x8_text(value, 74, y);
+        }
+
+        Role::Muted.set();
+        draw_centered_4x4_text("PRESS X TO RETURN", SCREEN_SIZE as i32 - 20);
+    }
+
+    fn update(&mut self, gamepad: &GamePad, audio_queue: &mut AudioQueue) -> Transition {
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+            return Transition::Noop;
+        }
+
+        if gamepad.x() {
+            self.save();
+            audio_queue.play(Sfx::MenuSelect.effect());
+            Transition::Pop
+        } else if gamepad.up() || gamepad.down() {
+            self.row = if gamepad.down() {
+                (self.row + 1) % ROWS.len()
+            } else {
+                (self.row + ROWS.len() - 1) % ROWS.len()
+            };
+            audio_queue.play(Sfx::MenuMove.effect());
+            self.cooldown = MAX_COOLDOWN;
+            Transition::Noop
+        } else if gamepad.left() || gamepad.right() {
+            self.change_row_value(gamepad.right());
+            audio_queue.play(Sfx::MenuMove.effect());
+            self.cooldown = MAX_COOLDOWN;
+            Transition::Noop
+        } else {
+            Transition::Noop
+        }
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "ON"
+    } else {
+        "OFF"
+    }
+}
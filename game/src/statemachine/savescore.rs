@@ -4,110 +4,170 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
 use wasm4fun_fmt::format_i32;
-use wasm4fun_graphics::{
-    draw_horizontal_line, draw_rect, draw_text, set_drawing_colors, SCREEN_SIZE,
-};
+use wasm4fun_graphics::{draw_horizontal_line, draw_rect, draw_text, SCREEN_SIZE};
 use wasm4fun_input::GamePad;
 
-use crate::audio::{menu_move_tone, menu_select_tone, AudioQueue};
-use crate::game::{HighScore, HighScores};
-use crate::graphics::draw_centered_5x8_text;
+use crate::audio::{AudioQueue, Sfx};
+use crate::font::draw_centered_5x8_text;
+use crate::game::{HighScore, Leaderboard, SaveData};
+use crate::keyboard::{Key, Keyboard};
+use crate::music::Music;
+use crate::theme::Role;
 
-use super::Transition;
+use super::{Effect, GameState, Transition};
 
 const MAX_COOLDOWN: u8 = 10;
 const MAX_LETTERS: usize = 3;
+const DEFAULT_NAME: [char; MAX_LETTERS] = ['A', 'A', 'A'];
 
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
 pub struct SaveScoreState {
+    leaderboard: Leaderboard,
     cooldown: u8,
-    index: usize,
+    length: usize,
     letters: [char; MAX_LETTERS],
+    keyboard: Keyboard,
     score: u32,
 }
 
 impl SaveScoreState {
-    pub fn new(score: u32) -> Self {
+    pub fn new(leaderboard: Leaderboard, score: u32) -> Self {
+        let letters = SaveData::load().last_name().unwrap_or(DEFAULT_NAME);
+
+        // The default name is already complete, so start from the end key
+        let mut keyboard = Keyboard::default();
+        keyboard.select(Key::End);
+
         Self {
+            leaderboard,
             cooldown: MAX_COOLDOWN,
-            index: 0,
-            letters: ['A'; MAX_LETTERS],
+            length: MAX_LETTERS,
+            letters,
+            keyboard,
             score,
         }
     }
 
-    pub fn draw(&self, _gamepad: Option<&GamePad>) {
-        set_drawing_colors(4);
+    fn press(&mut self, key: Key, audio_queue: &mut AudioQueue) -> Transition {
+        match key {
+            Key::Character(letter) => self.push_letter(letter),
+            Key::Space => self.push_letter(' '),
+            Key::Delete => {
+                self.length = self.length.saturating_sub(1);
+            }
+            Key::End => {
+                if self.length == 0 {
+                    return Transition::Noop;
+                }
+
+                for letter in self.letters.iter_mut().skip(self.length) {
+                    *letter = ' ';
+                }
+
+                let mut data = SaveData::load();
+                let highscore = HighScore::new(self.letters, self.score);
+                data.highscores_mut()
+                    .get_mut(self.leaderboard)
+                    .add(highscore);
+                data.set_last_name(self.letters);
+                data.save();
+                audio_queue.play(Sfx::MenuSelect.effect());
+
+                return Transition::PopN(2);
+            }
+        }
+
+        audio_queue.play(Sfx::MenuMove.effect());
+        Transition::Noop
+    }
+
+    fn push_letter(&mut self, letter: char) {
+        if self.length < MAX_LETTERS {
+            self.letters[self.length] = letter;
+            self.length += 1;
+        }
+
+        if self.length == MAX_LETTERS {
+            self.keyboard.select(Key::End);
+        }
+    }
+}
+
+impl GameState for SaveScoreState {
+    fn name(&self) -> &'static str {
+        "save_score"
+    }
+
+    fn music(&self) -> Option<Music> {
+        Some(Music::GameOver)
+    }
+
+    /// Wipe the screen open after the game
+    fn enter_effect(&self) -> Effect {
+        Effect::Wipe(20)
+    }
+
+    fn draw(&self, _gamepad: Option<&GamePad>) {
+        Role::Backdrop.set();
         draw_rect(0, 0, SCREEN_SIZE, SCREEN_SIZE);
 
-        set_drawing_colors(1);
+        Role::Text.set();
 
         let text = "Achieved high score!";
-        let y = ((SCREEN_SIZE - 72) / 2) as i32;
-        draw_centered_5x8_text(text, y);
+        draw_centered_5x8_text(text, 8);
 
         let mut buffer = [0; 10];
         let text = format_i32(&mut buffer, self.score as i32);
-        let y = ((SCREEN_SIZE - 48) / 2) as i32;
-        draw_centered_5x8_text(text, y);
+        draw_centered_5x8_text(text, 20);
 
         let text = "Enter name:";
-        let y = ((SCREEN_SIZE - 16) / 2) as i32;
-        draw_centered_5x8_text(text, y);
+        draw_centered_5x8_text(text, 36);
 
         let x = ((SCREEN_SIZE - 5 * 8) / 2) as i32;
-        let y = ((SCREEN_SIZE + 16) / 2) as i32;
+        let y = 50;
 
         let mut tmp = [0u8; 1];
 
-        for (i, letter) in self.letters.iter().enumerate() {
+        for (i, letter) in self.letters.iter().take(self.length).enumerate() {
             let s = letter.encode_utf8(&mut tmp);
             draw_text(s, x + 16 * i as i32, y);
         }
 
-        draw_horizontal_line(x + 16 * self.index as i32 - 2, y + 8, 11);
-        draw_horizontal_line(x + 16 * self.index as i32 - 2, y + 9, 11);
+        if self.length < MAX_LETTERS {
+            draw_horizontal_line(x + 16 * self.length as i32 - 2, y + 8, 11);
+            draw_horizontal_line(x + 16 * self.length as i32 - 2, y + 9, 11);
+        }
+
+        self.keyboard.draw(10, 90);
     }
 
-    pub fn update(&mut self, gamepad: &GamePad, audio_queue: &mut AudioQueue) -> Transition {
+    fn update(&mut self, gamepad: &GamePad, audio_queue: &mut AudioQueue) -> Transition {
         if self.cooldown == 0 {
             if gamepad.z() {
-                let mut highscores = HighScores::load();
-                let highscore = HighScore::new(self.letters, self.score);
-                highscores.add(highscore);
-                highscores.save();
-                audio_queue.play(menu_select_tone());
-
-                return Transition::PopN(2);
+                self.cooldown = MAX_COOLDOWN;
+                return self.press(self.keyboard.selected(), audio_queue);
+            } else if gamepad.x() {
+                self.cooldown = MAX_COOLDOWN;
+                return self.press(Key::Delete, audio_queue);
             } else if gamepad.down() {
-                let mut letter = self.letters[self.index] as u8;
-                letter += 1;
-                if letter > b'Z' {
-                    letter = b'A';
-                }
-                self.letters[self.index] = letter as char;
-                audio_queue.play(menu_move_tone());
+                self.keyboard.move_down();
+                audio_queue.play(Sfx::MenuMove.effect());
                 self.cooldown = MAX_COOLDOWN;
             } else if gamepad.up() {
-                let mut letter = self.letters[self.index] as u8;
-                letter -= 1;
-                if letter < b'A' {
-                    letter = b'Z';
-                }
-                self.letters[self.index] = letter as char;
-                audio_queue.play(menu_move_tone());
+                self.keyboard.move_up();
+                audio_queue.play(Sfx::MenuMove.effect());
                 self.cooldown = MAX_COOLDOWN;
             } else if gamepad.left() {
-                if self.index > 0 {
-                    self.index -= 1;
-                }
-                audio_queue.play(menu_move_tone());
+                self.keyboard.move_left();
+                audio_queue.play(Sfx::MenuMove.effect());
                 self.cooldown = MAX_COOLDOWN;
             } else if gamepad.right() {
-                if self.index < MAX_LETTERS - 1 {
-                    self.index += 1;
-                }
-                audio_queue.play(menu_move_tone());
+                self.keyboard.move_right();
+                audio_queue.play(Sfx::MenuMove.effect());
                 self.cooldown = MAX_COOLDOWN;
             }
         }
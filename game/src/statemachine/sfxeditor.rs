@@ -0,0 +1,269 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use wasm4fun_fmt::format_i32;
+use wasm4fun_graphics::{draw_4x4_text, draw_centered_4x4_text, draw_rect, SCREEN_SIZE};
+use wasm4fun_input::GamePad;
+use wasm4fun_log::debug;
+
+use crate::audio::{AudioQueue, Channel, Duty, Priority, Sfx, SfxDefinition, SFX_TABLE};
+use crate::font::draw_centered_5x8_text;
+use crate::theme::Role;
+
+use super::{GameState, Transition};
+
+/// The labels of the editable rows, the first one selects the sound effect
+const ROWS: [&str; 11] = [
+    "SFX", "FREQ1", "FREQ2", "ATTACK", "DECAY", "SUSTAIN", "RELEASE", "VOLUME", "CHANNEL", "DUTY",
+    "PRIORITY",
+];
+
+/// The vertical distance between rows
+const ROW_HEIGHT: i32 = 10;
+
+/// The frequency change for each step
+const FREQUENCY_STEP: u16 = 10;
+
+/// The maximal volume accepted by WASM-4
+const MAX_VOLUME: u8 = 100;
+
+/// The number of frames before a held button starts repeating
+const REPEAT_DELAY: u8 = 20;
+
+/// The number of frames between repetitions of a held button
+const REPEAT_INTERVAL: u8 = 3;
+
+/// The size of the buffer for storage bytes export
+#[cfg(feature = "storage")]
+const EXPORT_SIZE: usize = 256;
+
+/// A debug state for tweaking the sound effects table
+///
+/// The player edits a copy of [`SFX_TABLE`] and previews each sound effect
+/// through the audio queue.
+/// The edited table can be dumped to the debug log either as Rust source, to
+/// be pasted back into `audio.rs`, or as storage bytes.
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct SfxEditorState {
+    table: [SfxDefinition; Sfx::COUNT],
+    sfx: usize,
+    row: usize,
+    held: u8,
+    chord_pressed: bool,
+}
+
+impl SfxEditorState {
+    pub fn new() -> Self {
+        Self {
+            table: SFX_TABLE,
+            sfx: 0,
+            row: 0,
+            held: 0,
+            chord_pressed: true,
+        }
+    }
+
+    fn definition(&self) -> &SfxDefinition {
+        &self.table[self.sfx]
+    }
+
+    fn choice(&self, row: usize) -> &'static str {
+        let definition = self.definition();
+        match row {
+            0 => Sfx::from_index(self.sfx).name(),
+            8 => match definition.channel {
+                Channel::Pulse1 => "PULSE 1",
+                Channel::Pulse2 => "PULSE 2",
+                Channel::Triangle => "TRIANGLE",
+                Channel::Noise => "NOISE",
+            },
+            9 => match definition.duty_cycle {
+                Duty::OneEighth => "1/8",
+                Duty::OneQuarter => "1/4",
+                Duty::Half => "1/2",
+                Duty::ThreeQuarters => "3/4",
+            },
+            _ => match definition.priority {
+                Priority::Low => "LOW",
+                Priority::Medium => "MEDIUM",
+                Priority::High => "HIGH",
+                Priority::Critical => "CRITICAL",
+            },
+        }
+    }
+
+    fn change(&mut self, delta: i32) {
+        let sfx = self.sfx;
+        let definition = &mut self.table[sfx];
+        match self.row {
+            0 => self.sfx = wrap(sfx, delta, Sfx::COUNT),
+            1 => definition.first_frequency = step_frequency(definition.first_frequency, delta),
+            2 => definition.second_frequency = step_frequency(definition.second_frequency, delta),
+            3 => definition.attack = step(definition.attack, delta, u8::MAX),
+            4 => definition.decay = step(definition.decay, delta, u8::MAX),
+            5 => definition.sustain = step(definition.sustain, delta, u8::MAX),
+            6 => definition.release = step(definition.release, delta, u8::MAX),
+            7 => definition.volume = step(definition.volume, delta, MAX_VOLUME),
+            8 => {
+                let index = wrap(definition.channel.index(), delta, Channel::COUNT);
+                definition.channel = Channel::from_index(index);
+            }
+            9 => {
+                let index = wrap(definition.duty_cycle.index(), delta, Duty::COUNT);
+                definition.duty_cycle = Duty::from_index(index);
+            }
+            _ => {
+                let index = wrap(definition.priority.index(), delta, Priority::COUNT);
+                definition.priority = Priority::from_index(index);
+            }
+        }
+    }
+
+    fn export_source(&self) {
+        debug!("pub const SFX_TABLE: [SfxDefinition; Sfx::COUNT] = [");
+        for (i, definition) in self.table.iter().enumerate() {
+            debug!("    // {}", Sfx::from_index(i).name());
+            debug!("    SfxDefinition {{");
+            debug!("        first_frequency: {},", definition.first_frequency);
+            debug!("        second_frequency: {},", definition.second_frequency);
+            debug!("        attack: {},", definition.attack);
+            debug!("        decay: {},", definition.decay);
+            debug!("        sustain: {},", definition.sustain);
+            debug!("        release: {},", definition.release);
+            debug!("        volume: {},", definition.volume);
+            debug!("        channel: Channel::{:?},", definition.channel);
+            debug!("        duty_cycle: Duty::{:?},", definition.duty_cycle);
+            debug!("        priority: Priority::{:?},", definition.priority);
+            debug!("    }},");
+        }
+        debug!("];");
+    }
+
+    #[cfg(feature = "storage")]
+    fn export_bytes(&self) {
+        let mut buffer = [0; EXPORT_SIZE];
+        match postcard::to_slice(&self.table, &mut buffer) {
+            Ok(bytes) => {
+                debug!("Sound effects table of {} bytes:", bytes.len());
+                crate::debugoverlay::dump_hex(bytes);
+            }
+            Err(error) => {
+                debug!("Could not serialize sound effects table: {}", error);
+            }
+        }
+    }
+
+    #[cfg(not(feature = "storage"))]
+    fn export_bytes(&self) {
+        debug!("Storage not enabled");
+    }
+}
+
+impl GameState for SfxEditorState {
+    fn name(&self) -> &'static str {
+        "sfx editor"
+    }
+
+    fn draw(&self, _gamepad: Option<&GamePad>) {
+        Role::Backdrop.set();
+        draw_rect(0, 0, SCREEN_SIZE, SCREEN_SIZE);
+
+        Role::Accent.set();
+        draw_centered_5x8_text("SFX EDITOR", 8);
+
+        let definition = self.definition();
+        for (i, label) in ROWS.iter().enumerate() {
+            let y = 28 + i as i32 * ROW_HEIGHT;
+            if i == self.row {
+                Role::Accent.set();
+                draw_4x4_text(">", 8, y);
+            } else {
+                Role::Muted.set();
+            }
+
+            let mut buffer = [0; 12];
+            let value = match i {
+                1 => format_i32(&mut buffer, definition.first_frequency as i32),
+                2 => format_i32(&mut buffer, definition.second_frequency as i32),
+                3 => format_i32(&mut buffer, definition.attack as i32),
+                4 => format_i32(&mut buffer, definition.decay as i32),
+                5 => format_i32(&mut buffer, definition.sustain as i32),
+                6 => format_i32(&mut buffer, definition.release as i32),
+                7 => format_i32(&mut buffer, definition.volume as i32),
+                _ => self.choice(i),
+            };
+            draw_4x4_text(label, 16, y);
+            draw_4x4_text(value, 64, y);
+        }
+
+        Role::Muted.set();
+        draw_centered_4x4_text("Z PREVIEW   X+DOWN CLOSE", 144);
+        draw_centered_4x4_text("X+LEFT SOURCE   X+RIGHT BYTES", 150);
+    }
+
+    fn update(&mut self, gamepad: &GamePad, audio_queue: &mut AudioQueue) -> Transition {
+        let export_source = gamepad.x() && gamepad.left();
+        let export_bytes = gamepad.x() && gamepad.right();
+        let close = gamepad.x() && gamepad.down();
+        let preview = !gamepad.x() && gamepad.z();
+        let up = !gamepad.x() && gamepad.up();
+        let down = !gamepad.x() && gamepad.down();
+        let command = export_source || export_bytes || close || preview || up || down;
+
+        if !self.chord_pressed {
+            if close {
+                return Transition::Pop;
+            } else if export_source {
+                self.export_source();
+            } else if export_bytes {
+                self.export_bytes();
+            } else if preview {
+                audio_queue.play(self.definition().effect());
+            } else if up {
+                self.row = wrap(self.row, -1, ROWS.len());
+            } else if down {
+                self.row = wrap(self.row, 1, ROWS.len());
+            }
+        }
+        self.chord_pressed = command;
+
+        let delta = match (gamepad.x(), gamepad.left(), gamepad.right()) {
+            (false, true, false) => -1,
+            (false, false, true) => 1,
+            _ => 0,
+        };
+        if delta == 0 {
+            self.held = 0;
+        } else {
+            // Apply on press, then repeat while the button is held
+            if self.held == 0 || self.held == REPEAT_DELAY {
+                self.change(delta);
+            }
+            self.held = if self.held == REPEAT_DELAY {
+                REPEAT_DELAY - REPEAT_INTERVAL + 1
+            } else {
+                self.held + 1
+            };
+        }
+
+        Transition::Noop
+    }
+}
+
+fn wrap(index: usize, delta: i32, count: usize) -> usize {
+    (index as i32 + delta).rem_euclid(count as i32) as usize
+}
+
+fn step(value: u8, delta: i32, max: u8) -> u8 {
+    (value as i32 + delta).clamp(0, max as i32) as u8
+}
+
+fn step_frequency(value: u16, delta: i32) -> u16 {
+    (value as i32 + delta * FREQUENCY_STEP as i32).clamp(0, u16::MAX as i32) as u16
+}
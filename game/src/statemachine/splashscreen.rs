@@ -4,14 +4,18 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
 use wasm4fun_input::GamePad;
 use wasm4fun_logo::draw_logo;
 use wasm4fun_time::Ticker;
 
 use crate::audio::AudioQueue;
 
-use super::Transition;
+use super::{Effect, GameState, Transition};
 
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
 pub struct SplashScreenState {}
 
 impl SplashScreenState {
@@ -19,12 +23,23 @@ impl SplashScreenState {
     pub fn new() -> Self {
         Self {}
     }
+}
+
+impl GameState for SplashScreenState {
+    fn name(&self) -> &'static str {
+        "splashscreen"
+    }
+
+    /// Fade out to the main menu
+    fn exit_effect(&self) -> Effect {
+        Effect::Fade(30)
+    }
 
-    pub fn draw(&self, _gamepad: Option<&GamePad>) {
+    fn draw(&self, _gamepad: Option<&GamePad>) {
         draw_logo(Ticker.since_startup());
     }
 
-    pub fn update(&mut self, _gamepad: &GamePad, _audio_queue: &mut AudioQueue) -> Transition {
+    fn update(&mut self, _gamepad: &GamePad, _audio_queue: &mut AudioQueue) -> Transition {
         let delay = if cfg!(feature = "skip") { 0 } else { 240 };
 
         if Ticker.since_startup() > delay {
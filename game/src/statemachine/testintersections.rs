@@ -4,54 +4,257 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+#[cfg(target_family = "wasm")]
+use micromath::F32Ext;
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use tinyvec::{array_vec, ArrayVec};
+
 use wasm4fun_graphics::*;
 use wasm4fun_input::GamePad;
 
+use crate::assets::BRICK_SPRITE;
 use crate::audio::AudioQueue;
+use crate::game::{Ball, Bar, Brick, Camera, Combo, Difficulty, Playfield, MAX_BRICKS};
+use crate::graphics::draw_numbers;
+use crate::math::Contact;
+use crate::theme::Role;
 
-use crate::math::*;
+use super::{GameState, Transition};
 
-use super::Transition;
+const BRICK_HEALTH: u8 = 3;
+const ROTATION_STEP: f32 = core::f32::consts::PI / 12.0;
+const LINE_HEIGHT: i32 = 6;
 
-pub struct TestIntersectionsState {}
+/// An interactive sandbox for the ball collisions
+///
+/// The test ball is moved and bounced by the same routines used in game.
+///
+/// * Arrows move the ball by one pixel;
+/// * X + Left / Right rotates the ball direction;
+/// * X + Up places or removes a brick in the cell under the ball;
+/// * Z steps the simulation by one frame;
+/// * X + Z toggles running the simulation continuously.
+///
+/// After each bounce the contact point, the surface normal and the reflected
+/// velocity are shown.
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub struct TestIntersectionsState {
+    playfield: Playfield,
+    ball: Ball,
+    bar: Bar,
+    bricks: ArrayVec<[Brick; MAX_BRICKS]>,
+    combo: Combo,
+    angle: f32,
+    running: bool,
+    chord_pressed: bool,
+
+    contact: Option<Contact>,
+    incoming: (f32, f32),
+}
 
 impl TestIntersectionsState {
     #[allow(clippy::new_without_default)]
     #[allow(unused)]
     pub fn new() -> Self {
-        Self {}
+        let playfield = Playfield::DEFAULT;
+        let mut state = Self {
+            playfield,
+            ball: Ball::new((0.0, -1.0), Difficulty::default().ball_speed(), &playfield),
+            bar: Bar::new(&playfield),
+            bricks: array_vec!(),
+            combo: Combo::default(),
+            angle: 0.0,
+            running: false,
+            chord_pressed: false,
+            contact: None,
+            incoming: (0.0, 0.0),
+        };
+        state.rotate(0.0);
+        state
     }
 
-    pub fn draw(&self, _gamepad: Option<&GamePad>) {
-        let (xs1, ys1) = (35, 38);
-        let (xs2, ys2) = (85, 38);
+    fn rotate(&mut self, delta: f32) {
+        self.angle += delta;
+        self.ball
+            .set_unit_velocity((self.angle.sin(), -self.angle.cos()));
+    }
 
-        let (xs3, ys3) = (122, 38);
-        let (xs4, ys4) = (122, 91);
+    fn move_ball(&mut self, gamepad: &GamePad) {
+        let (mut x, mut y) = self.ball.coordinates();
+        if gamepad.left() {
+            x -= 1.0;
+        }
+        if gamepad.right() {
+            x += 1.0;
+        }
+        if gamepad.up() {
+            y -= 1.0;
+        }
+        if gamepad.down() {
+            y += 1.0;
+        }
+        let x = x.clamp(0.0, self.playfield.width() as f32);
+        let y = y.clamp(0.0, self.playfield.height() as f32);
+        self.ball.set_coordinates((x, y));
+    }
 
-        let r = 8;
+    fn toggle_brick(&mut self) {
+        let (x, y) = self.ball.coordinates();
+        let (width, height) = (Brick::WIDTH as u8, Brick::HEIGHT as u8);
+        let columns = self.playfield.width() as u8 / width;
+        let column = (x as u8 / width).min(columns - 1);
+        let cell = (column * width, y as u8 / height * height);
 
-        for xc in 0..SCREEN_SIZE {
-            for yc in 0..SCREEN_SIZE {
-                if intersects_horizontal_segment((xc as i32, yc as i32), r, xs1, xs2, ys1).is_some()
-                    || intersects_vertical_segment((xc as i32, yc as i32), r, ys3, ys4, xs3)
-                        .is_some()
-                {
-                    set_drawing_colors(0x01);
-                } else {
-                    set_drawing_colors(0x03);
+        let position = self
+            .bricks
+            .iter()
+            .position(|brick| brick.position() == cell);
+        match position {
+            Some(index) => {
+                self.bricks.remove(index);
+            }
+            None => {
+                if self.bricks.len() < self.bricks.capacity() {
+                    self.bricks.push(Brick::new(cell.0, cell.1, BRICK_HEALTH));
                 }
-
-                draw_point(xc as i32, yc as i32);
             }
         }
+    }
+
+    fn step(&mut self, audio_queue: &mut AudioQueue) {
+        let previous_contact = self.ball.last_contact();
+        let incoming = self.ball.velocity();
+
+        self.ball.update_position();
+        self.ball.handle_collisions(
+            &self.playfield,
+            &self.bar,
+            &mut self.bricks,
+            &mut self.combo,
+            audio_queue,
+        );
+        self.combo.take_finished();
+        self.ball.ensure_moves_vertically();
+        self.ball.normalize_velocity();
+        self.bricks.retain(|brick| brick.is_solid());
+
+        let contact = self.ball.last_contact();
+        if contact != previous_contact {
+            self.contact = contact;
+            self.incoming = incoming;
+        }
+    }
+
+    fn draw_board(&self) {
+        Role::Outline.set();
+        let (left, top) = self.playfield.to_screen(0, 0);
+        draw_rect(
+            left - 1,
+            top - 1,
+            self.playfield.width() + 2,
+            self.playfield.height() + 2,
+        );
+
+        Role::Sprite.set();
+        for brick in self.bricks.iter() {
+            let (x, y) = brick.position();
+            let (x, y) = self.playfield.to_screen(x as i32, y as i32);
+            BRICK_SPRITE
+                .clip(0, 0, Brick::WIDTH, Brick::HEIGHT)
+                .blit(x, y);
+        }
+
+        let camera = Camera::default();
+        self.bar.draw(&camera, &self.playfield);
+        self.ball.draw(&camera, &self.playfield);
+    }
+
+    fn draw_vectors(&self) {
+        let (x, y) = self.ball.coordinates();
+        let (vx, vy) = self.ball.velocity();
+        let (x, y) = self.playfield.to_screen(x as i32, y as i32);
+        Role::Text.set();
+        draw_line(x, y, x + (vx * 0.2) as i32, y + (vy * 0.2) as i32);
+
+        if let Some(contact) = self.contact {
+            let (px, py) = contact.point;
+            let (nx, ny) = contact.normal;
+            let (px, py) = self.playfield.to_screen(px as i32, py as i32);
+
+            Role::Muted.set();
+            let (ix, iy) = self.incoming;
+            draw_line(px - (ix * 0.2) as i32, py - (iy * 0.2) as i32, px, py);
+
+            Role::Accent.set();
+            draw_line(px, py, px + (nx * 8.0) as i32, py + (ny * 8.0) as i32);
+            draw_rect(px - 1, py - 1, 3, 3);
+        }
+    }
+
+    fn draw_values(&self) {
+        Role::Text.set();
+
+        let (x, y) = self.ball.coordinates();
+        let (vx, vy) = self.ball.velocity();
+        draw_numbers("BALL", &[x as i32, y as i32], 2, 0);
+        draw_numbers("VEL", &[vx as i32, vy as i32], 80, 0);
 
-        set_drawing_colors(0x02);
-        draw_line(xs1, ys1, xs2, ys2);
-        draw_line(xs3, ys3, xs4, ys4);
+        if let Some(contact) = self.contact {
+            let (px, py) = contact.point;
+            let (nx, ny) = contact.normal;
+            let (ix, iy) = self.incoming;
+            draw_numbers("HIT", &[px as i32, py as i32], 2, LINE_HEIGHT);
+            draw_numbers("N", &[nx as i32, ny as i32], 50, LINE_HEIGHT);
+            draw_numbers("IN", &[ix as i32, iy as i32], 80, LINE_HEIGHT);
+        }
     }
+}
+
+impl GameState for TestIntersectionsState {
+    fn name(&self) -> &'static str {
+        "test_intersections"
+    }
+
+    fn draw(&self, _gamepad: Option<&GamePad>) {
+        self.draw_board();
+        self.draw_vectors();
+        self.draw_values();
+    }
+
+    fn update(&mut self, gamepad: &GamePad, audio_queue: &mut AudioQueue) -> Transition {
+        let toggle_running = gamepad.x() && gamepad.z();
+        let rotate_left = gamepad.x() && gamepad.left();
+        let rotate_right = gamepad.x() && gamepad.right();
+        let toggle_brick = gamepad.x() && gamepad.up();
+        let step = !gamepad.x() && gamepad.z();
+        let command = toggle_running || rotate_left || rotate_right || toggle_brick || step;
+
+        if !self.chord_pressed {
+            if toggle_running {
+                self.running = !self.running;
+            } else if rotate_left {
+                self.rotate(-ROTATION_STEP);
+            } else if rotate_right {
+                self.rotate(ROTATION_STEP);
+            } else if toggle_brick {
+                self.toggle_brick();
+            } else if step {
+                self.step(audio_queue);
+            }
+        }
+        self.chord_pressed = command;
+
+        if !gamepad.x() && !gamepad.z() {
+            self.move_ball(gamepad);
+        }
+
+        if self.running {
+            self.step(audio_queue);
+        }
 
-    pub fn update(&self, _gamepad: &GamePad, _audio_queue: &mut AudioQueue) -> Transition {
         Transition::Noop
     }
 }
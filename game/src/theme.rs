@@ -0,0 +1,196 @@
+// Copyright Claudio Mattera 2022.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Colour themes and semantic colour roles
+//!
+//! WASM-4 has a palette of four colours, and draw calls refer to them by
+//! index.
+//! Every theme lists its colours from lightest to darkest, so that draw code
+//! can use the same [`Role`] regardless of the theme.
+
+#[cfg(feature = "storage")]
+use serde::{Deserialize, Serialize};
+
+use wasm4fun_graphics::set_drawing_colors;
+
+use crate::graphics::set_palette;
+
+/// A named palette
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "storage", derive(Deserialize, Serialize))]
+pub enum Theme {
+    /// The default WASM-4 palette
+    #[default]
+    Classic,
+
+    /// Shades of grey
+    Grayscale,
+
+    /// Pure black and white, with saturated mid tones
+    HighContrast,
+
+    /// Blue and orange tones, distinguishable with the common colour vision
+    /// deficiencies
+    ColourBlind,
+
+    /// Warm golden tones
+    Gold,
+
+    /// Pastel pink tones
+    IceCream,
+}
+
+impl Theme {
+    /// The number of themes
+    pub const COUNT: usize = 6;
+
+    /// Return the theme at the given index
+    pub fn from_index(index: usize) -> Self {
+        match index % Self::COUNT {
+            0 => Theme::Classic,
+            1 => Theme::Grayscale,
+            2 => Theme::HighContrast,
+            3 => Theme::ColourBlind,
+            4 => Theme::Gold,
+            _ => Theme::IceCream,
+        }
+    }
+
+    /// Return the index of the theme
+    pub fn index(&self) -> usize {
+        match self {
+            Theme::Classic => 0,
+            Theme::Grayscale => 1,
+            Theme::HighContrast => 2,
+            Theme::ColourBlind => 3,
+            Theme::Gold => 4,
+            Theme::IceCream => 5,
+        }
+    }
+
+    /// Return the name of the theme
+    pub fn name(&self) -> &'static str {
+        match self {
+            Theme::Classic => "CLASSIC",
+            Theme::Grayscale => "GRAYSCALE",
+            Theme::HighContrast => "CONTRAST",
+            Theme::ColourBlind => "COLOUR BLIND",
+            Theme::Gold => "GOLD",
+            Theme::IceCream => "ICE CREAM",
+        }
+    }
+
+    /// Return the next theme, wrapping around
+    pub fn next(&self) -> Self {
+        Self::from_index(self.index() + 1)
+    }
+
+    /// Return the previous theme, wrapping around
+    pub fn previous(&self) -> Self {
+        Self::from_index(self.index() + Self::COUNT - 1)
+    }
+
+    /// Return the colours of the theme, from lightest to darkest
+    pub fn colours(&self) -> [u32; 4] {
+        match self {
+            Theme::Classic => [0xdef7cd, 0x86bf6b, 0x306950, 0x071821],
+            Theme::Grayscale => [0xffffff, 0xb6b6b6, 0x676767, 0x000000],
+            Theme::HighContrast => [0xffffff, 0xffd400, 0x0047ab, 0x000000],
+            Theme::ColourBlind => [0xfaf4e1, 0xe69f00, 0x0072b2, 0x1a1a1a],
+            Theme::Gold => [0xcfab51, 0x9d654c, 0x4d222c, 0x210b1b],
+            Theme::IceCream => [0xfff6d3, 0xf9a875, 0xeb6b6f, 0x7c3f58],
+        }
+    }
+
+    /// Return the theme to use for a level with an optional override
+    ///
+    /// Levels only override the classic theme, so that a theme explicitly
+    /// chosen by the player, e.g. for accessibility, is always kept.
+    pub fn with_override(self, level_theme: Option<Theme>) -> Self {
+        match (self, level_theme) {
+            (Theme::Classic, Some(level_theme)) => level_theme,
+            _ => self,
+        }
+    }
+
+    /// Set the theme colours as the current palette
+    pub fn apply(&self) {
+        set_palette(self.colours());
+    }
+}
+
+/// A semantic colour role for draw calls
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    /// Dark fill behind the board and full-screen states
+    Backdrop,
+
+    /// Light text over the dark backdrop
+    Text,
+
+    /// Titles, selected entries and highlighted values
+    Accent,
+
+    /// Secondary text, hints and shadows
+    Muted,
+
+    /// Dark text over a light background or an accent fill
+    Ink,
+
+    /// Sprites with a transparent background
+    Sprite,
+
+    /// Sprites covering their whole area
+    OpaqueSprite,
+
+    /// Sprites drawn as a solid shape in the accent colour
+    FlashSprite,
+
+    /// Sprites drawn as a solid shape in the secondary colour
+    MutedSprite,
+
+    /// Dark panel with an accent border
+    Panel,
+
+    /// Rectangle outlined with the accent colour
+    Outline,
+
+    /// Rectangle filled with the accent colour
+    AccentFill,
+
+    /// Rectangle filled with the secondary colour
+    MutedFill,
+
+    /// Lines and points covering the screen for dimming and transitions
+    Shade,
+}
+
+impl Role {
+    /// Return the WASM-4 draw colours of the role
+    pub fn draw_colors(&self) -> u16 {
+        match self {
+            Role::Backdrop => 0x44,
+            Role::Text => 0x01,
+            Role::Accent => 0x02,
+            Role::Muted => 0x03,
+            Role::Ink => 0x04,
+            Role::Sprite => 0x1230,
+            Role::OpaqueSprite => 0x1234,
+            Role::FlashSprite => 0x2220,
+            Role::MutedSprite => 0x3330,
+            Role::Panel => 0x24,
+            Role::Outline => 0x20,
+            Role::AccentFill => 0x22,
+            Role::MutedFill => 0x33,
+            Role::Shade => 0x4,
+        }
+    }
+
+    /// Set the role as the current draw colours
+    pub fn set(&self) {
+        set_drawing_colors(self.draw_colors());
+    }
+}